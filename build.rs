@@ -1,4 +1,13 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+const FONTS_DIR: &str = "assets/fonts";
+// Glyphs per row in the generated atlas.
+const ATLAS_COLUMNS: usize = 16;
+
 fn main() {
+    convert_fonts();
+
     linker_be_nice();
     // make sure linkall.x is the last linker script (otherwise might cause problems with flip-link)
     println!("cargo:rustc-link-arg=-Tlinkall.x");
@@ -54,3 +63,155 @@ fn linker_be_nice() {
         std::env::current_exe().unwrap().display()
     );
 }
+
+// Turns every `assets/fonts/*.font` into a `MonoFont` constant in `$OUT_DIR/fonts.rs`.
+// The file format is described at the top of the font files.
+fn convert_fonts() {
+    println!("cargo:rerun-if-changed={}", FONTS_DIR);
+
+    let mut out = String::new();
+    let mut entries: Vec<_> = std::fs::read_dir(FONTS_DIR)
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort();
+
+    for path in entries {
+        if path.extension().is_some_and(|ext| ext == "font") {
+            println!("cargo:rerun-if-changed={}", path.display());
+            out.push_str(&convert_font(&path));
+        }
+    }
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out_dir).join("fonts.rs"), out).unwrap();
+}
+
+fn convert_font(path: &Path) -> String {
+    let source = std::fs::read_to_string(path).unwrap();
+    let fail = |line: usize, msg: &str| -> ! {
+        panic!("{}:{}: {}", path.display(), line + 1, msg);
+    };
+
+    let mut name = None;
+    let mut size = None;
+    let mut baseline = None;
+    let mut spacing = 0;
+    let mut replacement = None;
+    let mut glyphs: Vec<(char, Vec<u8>)> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let key = fields.next().unwrap();
+        let mut number = |radix: u32| -> u32 {
+            let field = fields
+                .next()
+                .unwrap_or_else(|| fail(index, "missing value"));
+            u32::from_str_radix(field, radix).unwrap_or_else(|_| fail(index, "bad number"))
+        };
+
+        match key {
+            "name" => name = Some(line["name".len()..].trim().to_string()),
+            "size" => size = Some((number(10), number(10))),
+            "baseline" => baseline = Some(number(10)),
+            "spacing" => spacing = number(10),
+            "replacement" => replacement = char::from_u32(number(16)),
+            codepoint => {
+                let ch = u32::from_str_radix(codepoint, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .unwrap_or_else(|| fail(index, "bad codepoint"));
+                if ch == '\0' {
+                    fail(index, "NUL is reserved by the glyph mapping");
+                }
+                let bytes = fields
+                    .map(|field| u8::from_str_radix(field, 16))
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap_or_else(|_| fail(index, "bad glyph byte"));
+                glyphs.push((ch, bytes));
+            }
+        }
+    }
+
+    let name = name.unwrap_or_else(|| panic!("{}: missing `name`", path.display()));
+    let (width, height) = size.unwrap_or_else(|| panic!("{}: missing `size`", path.display()));
+    let baseline = baseline.unwrap_or(height - 1);
+    let (width, height) = (width as usize, height as usize);
+    let bytes_per_column = height.div_ceil(8);
+
+    // One bit per pixel, rows padded to whole bytes, as `ImageRaw<BinaryColor>` wants it.
+    let atlas_width = ATLAS_COLUMNS * width;
+    let atlas_rows = glyphs.len().div_ceil(ATLAS_COLUMNS);
+    let stride = atlas_width.div_ceil(8);
+    let mut atlas = vec![0u8; stride * atlas_rows * height];
+
+    for (index, (ch, columns)) in glyphs.iter().enumerate() {
+        if columns.len() != width * bytes_per_column {
+            panic!(
+                "{}: glyph {:?} has {} bytes, expected {}",
+                path.display(),
+                ch,
+                columns.len(),
+                width * bytes_per_column
+            );
+        }
+        let origin_x = (index % ATLAS_COLUMNS) * width;
+        let origin_y = (index / ATLAS_COLUMNS) * height;
+
+        for x in 0..width {
+            for y in 0..height {
+                let byte = columns[x * bytes_per_column + y / 8];
+                if byte & (1 << (y % 8)) != 0 {
+                    let px = origin_x + x;
+                    let row = origin_y + y;
+                    atlas[row * stride + px / 8] |= 0x80 >> (px % 8);
+                }
+            }
+        }
+    }
+
+    let chars: String = glyphs.iter().map(|(ch, _)| *ch).collect();
+    let replacement_index = replacement
+        .and_then(|replacement| chars.chars().position(|ch| ch == replacement))
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    writeln!(out, "// Generated from {} by build.rs.", path.display()).unwrap();
+    writeln!(out, "pub const {}: MonoFont<'static> = MonoFont {{", name).unwrap();
+    writeln!(
+        out,
+        "    image: ImageRaw::new(&{:?}, {}),",
+        atlas, atlas_width
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    glyph_mapping: &StrGlyphMapping::new({:?}, {}),",
+        chars, replacement_index
+    )
+    .unwrap();
+    writeln!(out, "    character_size: Size::new({}, {}),", width, height).unwrap();
+    writeln!(out, "    character_spacing: {},", spacing).unwrap();
+    writeln!(out, "    baseline: {},", baseline).unwrap();
+    writeln!(
+        out,
+        "    underline: DecorationDimensions::new({}, 1),",
+        (baseline as usize + 2).min(height - 1)
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    strikethrough: DecorationDimensions::new({}, 1),",
+        height / 2
+    )
+    .unwrap();
+    writeln!(out, "}};").unwrap();
+    out
+}
@@ -0,0 +1,70 @@
+use embedded_graphics::pixelcolor::{Rgb565, raw::RawU16};
+use embedded_graphics::prelude::{RawData, RgbColor};
+
+// Color changes applied while a texture is drawn, so variants of a sprite (hit flashes,
+// team colors, tints) need no texture of their own. Applied in order: palette swap,
+// brightness, flash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorEffect {
+    swap: &'static [(Rgb565, Rgb565)],
+    // Percent, above 100 brightens until the channels saturate.
+    brightness: u8,
+    // Percent of the way to white.
+    flash: u8,
+}
+
+impl ColorEffect {
+    pub const NONE: ColorEffect = ColorEffect {
+        swap: &[],
+        brightness: 100,
+        flash: 0,
+    };
+
+    // Every color equal to the first of a pair is drawn as the second.
+    pub fn with_swap(mut self, swap: &'static [(Rgb565, Rgb565)]) -> Self {
+        self.swap = swap;
+        self
+    }
+
+    pub fn with_brightness(mut self, percent: u8) -> Self {
+        self.brightness = percent;
+        self
+    }
+
+    pub fn with_flash(mut self, percent: u8) -> Self {
+        self.flash = percent.min(100);
+        self
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    pub fn apply(&self, color: Rgb565) -> Rgb565 {
+        let color = self
+            .swap
+            .iter()
+            .find(|(from, _)| *from == color)
+            .map_or(color, |(_, to)| *to);
+
+        let channel = |value: u8, max: u8| {
+            let value = (value as u16 * self.brightness as u16 / 100).min(max as u16);
+            (value + (max as u16 - value) * self.flash as u16 / 100) as u8
+        };
+        Rgb565::new(
+            channel(color.r(), Rgb565::MAX_R),
+            channel(color.g(), Rgb565::MAX_G),
+            channel(color.b(), Rgb565::MAX_B),
+        )
+    }
+
+    pub fn apply_raw(&self, raw: u16) -> u16 {
+        RawU16::from(self.apply(Rgb565::from(RawU16::new(raw)))).into_inner()
+    }
+}
+
+impl Default for ColorEffect {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
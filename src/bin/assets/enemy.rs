@@ -0,0 +1,213 @@
+use crate::{
+    camera::Camera,
+    collision::{Aabb, SolidTiles, move_and_slide},
+    lcd::{DisplayError, LcdDisplay},
+};
+
+use super::{ColorEffect, Direction, Mob, MobBody, Texture};
+
+// Enemies only step every few updates so the player can outrun them.
+const FRAMES_PER_STEP: u8 = 3;
+// How long an enemy is drawn flashed after getting hit.
+const HIT_FLASH_FRAMES: u8 = 6;
+const HIT_FLASH_PERCENT: u8 = 80;
+// How close the target has to get before a patrolling enemy gives chase, in pixels.
+const PATROL_SIGHT: u16 = 64;
+
+#[derive(Clone, Copy)]
+pub enum EnemyAi {
+    // Walks back and forth between two panel positions.
+    Patrol { from: (u16, u16), to: (u16, u16) },
+    // Stands still until the target comes within `sight` pixels.
+    // It gives up once the target gets twice as far.
+    Guard { sight: u16 },
+}
+
+pub enum EnemyState {
+    Patrolling,
+    Chasing,
+}
+
+pub struct Enemy {
+    state: EnemyState,
+    ai: EnemyAi,
+    direction: Direction,
+    hp: u8,
+    contact_damage: u8,
+    velocity: u8,
+    body: MobBody,
+    sight: u16,
+    target: Option<Aabb>,
+    heading_to: bool,
+    frames: u8,
+    flash_frames: u8,
+}
+
+impl Enemy {
+    pub fn new(texture_map: &'static Texture, ai: EnemyAi) -> Self {
+        let sight = match ai {
+            EnemyAi::Patrol { .. } => PATROL_SIGHT,
+            EnemyAi::Guard { sight } => sight,
+        };
+
+        Enemy {
+            state: EnemyState::Patrolling,
+            ai,
+            direction: Direction::None,
+            hp: 30,
+            contact_damage: 10,
+            velocity: 1,
+            body: MobBody::new(texture_map, 32),
+            sight,
+            target: None,
+            heading_to: true,
+            frames: 0,
+            flash_frames: 0,
+        }
+    }
+
+    pub fn contact_damage(&self) -> u8 {
+        self.contact_damage
+    }
+
+    pub fn damage(&mut self, amount: u8) {
+        self.hp = self.hp.saturating_sub(amount);
+        self.flash_frames = HIT_FLASH_FRAMES;
+    }
+
+    // Called every frame with the bounds of whatever the enemy should hunt.
+    pub fn track(&mut self, target: Aabb) {
+        self.target = Some(target);
+    }
+
+    fn distance_to(&self, target: &Aabb) -> u16 {
+        let (x, y) = self.bounds().center();
+        let (tx, ty) = target.center();
+        // Clamped, a far away target must not wrap around to look close.
+        ((tx - x).unsigned_abs() + (ty - y).unsigned_abs())
+            .try_into()
+            .unwrap_or(u16::MAX)
+    }
+
+    fn think(&mut self) {
+        if let Some(target) = self.target {
+            let distance = self.distance_to(&target);
+
+            match self.state {
+                EnemyState::Patrolling if distance <= self.sight => {
+                    self.state = EnemyState::Chasing;
+                }
+                EnemyState::Chasing if distance > self.sight.saturating_mul(2) => {
+                    self.state = EnemyState::Patrolling;
+                }
+                _ => {}
+            }
+        }
+
+        let (x, y) = self.bounds().center();
+        let goal = match self.state {
+            EnemyState::Chasing => self.target.map(|target| target.center()),
+            EnemyState::Patrolling => match self.ai {
+                EnemyAi::Patrol { from, to } => {
+                    let goal = if self.heading_to { to } else { from };
+                    if (x, y) == (goal.0 as i32, goal.1 as i32) {
+                        self.heading_to = !self.heading_to;
+                    }
+                    Some((goal.0 as i32, goal.1 as i32))
+                }
+                EnemyAi::Guard { .. } => None,
+            },
+        };
+
+        // One axis at a time, biggest gap first, so movement stays grid-like.
+        self.direction = match goal {
+            Some((gx, gy)) if (gx - x).abs() >= (gy - y).abs() && gx != x => {
+                if gx > x {
+                    Direction::Up
+                } else {
+                    Direction::Down
+                }
+            }
+            Some((_, gy)) if gy != y => {
+                if gy > y {
+                    Direction::Right
+                } else {
+                    Direction::Left
+                }
+            }
+            _ => Direction::None,
+        };
+    }
+}
+
+impl Mob for Enemy {
+    fn body(&self) -> &MobBody {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut MobBody {
+        &mut self.body
+    }
+
+    fn is_alive(&self) -> bool {
+        self.hp > 0
+    }
+
+    fn current_effect(&self) -> ColorEffect {
+        if self.flash_frames > 0 {
+            self.body.effect.with_flash(HIT_FLASH_PERCENT)
+        } else {
+            self.body.effect
+        }
+    }
+
+    fn update_state<'d, SPI, DC>(
+        &mut self,
+        tiles: &impl SolidTiles,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        if self.hp == 0 {
+            return Ok(());
+        }
+
+        // The flash has to come and go in place, even while the enemy waits for its step.
+        self.flash_frames = self.flash_frames.saturating_sub(1);
+        if self.current_effect() != self.body.drawn_effect
+            && let (Some(x), Some(y)) = (self.body.pos.x, self.body.pos.y)
+        {
+            self.draw(x, y, camera, display)?;
+        }
+
+        self.frames = (self.frames + 1) % FRAMES_PER_STEP;
+        if self.frames != 0 {
+            return Ok(());
+        }
+
+        self.think();
+
+        let old_pos = self.body.pos;
+        let (dx, dy) = self.direction.delta(self.velocity);
+        let (bounds, collision) = move_and_slide(self.bounds(), dx, dy, tiles);
+
+        // Blocked while patrolling: turn around instead of pushing into the wall forever.
+        if collision.any() && matches!(self.state, EnemyState::Patrolling) {
+            self.heading_to = !self.heading_to;
+        }
+        if bounds == self.bounds() {
+            return Ok(());
+        }
+        if let Some(target) = self.target
+            && bounds.intersects(&target)
+        {
+            return Ok(());
+        }
+
+        let (x, y) = bounds.center();
+        self.draw_and_clean_dirty_pixels(old_pos, x as u16, y as u16, camera, display)
+    }
+}
@@ -1,40 +1,272 @@
 use alloc::string::String;
-use alloc::vec::Vec;
-use embedded_graphics::pixelcolor::Rgb565;
 
+mod effect;
+mod enemy;
+mod pickup;
 mod player;
-use mipidsi::{Display, NoResetPin, interface::SpiInterface, models::ILI9341Rgb565};
+mod projectile;
+mod sprites;
+mod texture;
+pub use effect::ColorEffect;
+pub use enemy::{Enemy, EnemyAi};
+pub use pickup::{Pickup, PickupKind};
 pub use player::Player;
+pub use projectile::{PROJECTILE_SIZE, Projectile};
+pub use sprites::Sprites;
+pub use texture::Texture;
 
-use crate::assets::player::PlayerPos;
+use crate::camera::Camera;
+use crate::collision::{Aabb, SolidTiles};
+use crate::lcd::{BACKGROUND_COLOR, DisplayError, LcdDisplay};
+
+#[derive(Clone, Copy)]
+pub struct MobPos {
+    x: Option<u16>,
+    y: Option<u16>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    None,
+    Up,
+    Left,
+    Right,
+    Down,
+}
+
+impl Direction {
+    // Panel axes: "up" grows x, "right" grows y.
+    pub fn delta(&self, velocity: u8) -> (i32, i32) {
+        let velocity = velocity as i32;
+        match self {
+            Direction::None => (0, 0),
+            Direction::Up => (velocity, 0),
+            Direction::Down => (-velocity, 0),
+            Direction::Left => (0, -velocity),
+            Direction::Right => (0, velocity),
+        }
+    }
+}
+
+// What every mob has on screen: where it is, its square texture and the effect to draw
+// it with. Textures are loaded once and shared, see `Sprites`.
+pub struct MobBody {
+    pos: MobPos,
+    width: u16,
+    texture_map: &'static Texture,
+    effect: ColorEffect,
+    drawn_effect: ColorEffect,
+}
+
+impl MobBody {
+    pub fn new(texture_map: &'static Texture, width: u16) -> Self {
+        MobBody {
+            pos: MobPos { x: None, y: None },
+            width,
+            texture_map,
+            effect: ColorEffect::NONE,
+            drawn_effect: ColorEffect::NONE,
+        }
+    }
+
+    fn bounds_at(&self, x: u16, y: u16) -> Aabb {
+        Aabb::from_center(x, y, self.width, self.width)
+    }
+}
 
 pub trait Mob {
-    fn new(texture_map: Vec<Rgb565>) -> Self;
+    fn body(&self) -> &MobBody;
+
+    fn body_mut(&mut self) -> &mut MobBody;
+
+    fn with_position(mut self, x: u16, y: u16) -> Self
+    where
+        Self: Sized,
+    {
+        let pos = &mut self.body_mut().pos;
+        pos.x.replace(x);
+        pos.y.replace(y);
+        self
+    }
+
+    fn with_effect(mut self, effect: ColorEffect) -> Self
+    where
+        Self: Sized,
+    {
+        self.body_mut().effect = effect;
+        self
+    }
+
+    fn bounds(&self) -> Aabb {
+        let body = self.body();
+        body.bounds_at(
+            body.pos.x.unwrap_or_default(),
+            body.pos.y.unwrap_or_default(),
+        )
+    }
 
+    // Dead mobs get despawned by the `EntityManager` at the end of the frame.
+    fn is_alive(&self) -> bool {
+        true
+    }
+
+    // The effect the next `draw` uses, mobs that flash when hit change it for a while.
+    fn current_effect(&self) -> ColorEffect {
+        self.body().effect
+    }
+
+    // `x`/`y` is the center in world coordinates.
     fn draw<'d, SPI, DC>(
         &mut self,
         x: u16,
         y: u16,
-        display: &mut Display<SpiInterface<'d, SPI, DC>, ILI9341Rgb565, NoResetPin>,
-    ) where
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
         SPI: embedded_hal::spi::SpiDevice,
-        DC: embedded_hal::digital::OutputPin;
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let effect = self.current_effect();
+        let body = self.body_mut();
+        body.pos.x.replace(x);
+        body.pos.y.replace(y);
+        body.drawn_effect = effect;
+
+        draw_texture(
+            body.bounds_at(x, y),
+            body.texture_map,
+            &body.drawn_effect,
+            camera,
+            display,
+        )
+    }
 
     fn update_state<'d, SPI, DC>(
         &mut self,
-        input: (u8, String),
-        display: &mut Display<SpiInterface<'d, SPI, DC>, ILI9341Rgb565, NoResetPin>,
-    ) where
+        tiles: &impl SolidTiles,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
         SPI: embedded_hal::spi::SpiDevice,
         DC: embedded_hal::digital::OutputPin;
 
     fn draw_and_clean_dirty_pixels<'d, SPI, DC>(
         &mut self,
-        old_pos: PlayerPos,
+        old_pos: MobPos,
         x: u16,
         y: u16,
-        display: &mut Display<SpiInterface<'d, SPI, DC>, ILI9341Rgb565, NoResetPin>,
-    ) where
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
         SPI: embedded_hal::spi::SpiDevice,
-        DC: embedded_hal::digital::OutputPin;
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let old_bounds = self
+            .body()
+            .bounds_at(old_pos.x.unwrap_or(x), old_pos.y.unwrap_or(y));
+
+        self.draw(x, y, camera, display)?;
+        clean_dirty_pixels(old_bounds, self.bounds(), camera, display)
+    }
+}
+
+// Mobs that are driven by a controller instead of AI.
+pub trait Controllable {
+    fn handle_input(&mut self, input: (u8, String));
+}
+
+fn draw_texture<'d, SPI, DC>(
+    bounds: Aabb,
+    texture: &Texture,
+    effect: &ColorEffect,
+    camera: &Camera,
+    display: &mut LcdDisplay<'d, SPI, DC>,
+) -> Result<(), DisplayError>
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    let mut palette = [0u16; 256];
+    camera.draw(bounds, texture.pixels(effect, &mut palette), display)
+}
+
+// Repaints the part of `old` that `new` no longer covers with the background.
+fn clean_dirty_pixels<'d, SPI, DC>(
+    old: Aabb,
+    new: Aabb,
+    camera: &Camera,
+    display: &mut LcdDisplay<'d, SPI, DC>,
+) -> Result<(), DisplayError>
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    if !old.intersects(&new) {
+        return fill_background(old, camera, display);
+    }
+
+    if new.left() > old.left() {
+        fill_background(
+            Aabb::new(
+                old.left(),
+                old.top(),
+                (new.left() - old.left()) as u16,
+                old.height,
+            ),
+            camera,
+            display,
+        )?;
+    }
+    if new.right() < old.right() {
+        fill_background(
+            Aabb::new(
+                new.right(),
+                old.top(),
+                (old.right() - new.right()) as u16,
+                old.height,
+            ),
+            camera,
+            display,
+        )?;
+    }
+    if new.top() > old.top() {
+        fill_background(
+            Aabb::new(
+                old.left(),
+                old.top(),
+                old.width,
+                (new.top() - old.top()) as u16,
+            ),
+            camera,
+            display,
+        )?;
+    }
+    if new.bottom() < old.bottom() {
+        fill_background(
+            Aabb::new(
+                old.left(),
+                new.bottom(),
+                old.width,
+                (old.bottom() - new.bottom()) as u16,
+            ),
+            camera,
+            display,
+        )?;
+    }
+    Ok(())
+}
+
+fn fill_background<'d, SPI, DC>(
+    area: Aabb,
+    camera: &Camera,
+    display: &mut LcdDisplay<'d, SPI, DC>,
+) -> Result<(), DisplayError>
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    camera.fill(area, BACKGROUND_COLOR, display)
 }
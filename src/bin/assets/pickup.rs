@@ -0,0 +1,55 @@
+use crate::{
+    camera::Camera,
+    collision::SolidTiles,
+    lcd::{DisplayError, LcdDisplay},
+};
+
+use super::{Mob, MobBody, Texture};
+
+#[derive(Clone, Copy, Debug)]
+pub enum PickupKind {
+    Heal(u8),
+    Score(u32),
+}
+
+pub struct Pickup {
+    kind: PickupKind,
+    body: MobBody,
+}
+
+impl Pickup {
+    pub fn new(texture_map: &'static Texture, kind: PickupKind) -> Self {
+        Pickup {
+            kind,
+            body: MobBody::new(texture_map, 16),
+        }
+    }
+
+    pub fn kind(&self) -> PickupKind {
+        self.kind
+    }
+}
+
+impl Mob for Pickup {
+    fn body(&self) -> &MobBody {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut MobBody {
+        &mut self.body
+    }
+
+    // Pickups just sit there until something walks over them.
+    fn update_state<'d, SPI, DC>(
+        &mut self,
+        _tiles: &impl SolidTiles,
+        _camera: &Camera,
+        _display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        Ok(())
+    }
+}
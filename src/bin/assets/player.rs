@@ -1,35 +1,38 @@
-use alloc::vec::Vec;
-use embedded_graphics::{
-    pixelcolor::Rgb565,
-    prelude::{RgbColor, WebColors},
-};
-use log::error;
+use alloc::string::String;
 
 use crate::{
-    inputs::{
-        NUMPAD_BUTTON_A, NUMPAD_BUTTON_B, NUMPAD_DOWN, NUMPAD_IDLE, NUMPAD_LEFT, NUMPAD_RIGHT,
-        NUMPAD_SELECT, NUMPAD_START, NUMPAD_UP,
-    },
-    utils::{vec_into_iram, vec_into_psram},
+    camera::Camera,
+    collision::{Aabb, SolidTiles, move_and_slide},
+    inputs::{NUMPAD_BUTTON_A, NUMPAD_DOWN, NUMPAD_LEFT, NUMPAD_RIGHT, NUMPAD_UP},
+    lcd::{DisplayError, LcdDisplay},
 };
 
-use super::Mob;
+use super::{ColorEffect, Controllable, Direction, Mob, MobBody, Texture, fill_background};
+
+// All timings are in game loop iterations.
+const INVULNERABLE_FRAMES: u16 = 120;
+const BLINK_FRAMES: u16 = 8;
+// The start of the invulnerability, drawn flashed.
+const HIT_FLASH_FRAMES: u16 = 6;
+const HIT_FLASH_PERCENT: u8 = 80;
+const KNOCKBACK_FRAMES: u8 = 8;
+const KNOCKBACK_VELOCITY: i32 = 2;
+const FIRE_COOLDOWN_FRAMES: u8 = 20;
 
 pub struct Player {
+    // Which pad moves it.
+    index: u8,
     state: PlayerState,
     direction: Direction,
     hp: u8,
     max_hp: u8,
     velocity: u8,
-    pos: PlayerPos,
-    texture_map: Vec<Rgb565>,
-    width: u16,
-}
-
-#[derive(Clone, Copy)]
-pub struct PlayerPos {
-    x: Option<u16>,
-    y: Option<u16>,
+    body: MobBody,
+    invulnerable_frames: u16,
+    knockback: (i32, i32),
+    knockback_frames: u8,
+    firing: bool,
+    fire_cooldown: u8,
 }
 
 pub enum PlayerState {
@@ -37,215 +40,184 @@ pub enum PlayerState {
     Moving,
 }
 
-pub enum Direction {
-    None,
-    Up,
-    Left,
-    Right,
-    Down,
-}
-
-impl Mob for Player {
-    fn new(texture_map: Vec<Rgb565>) -> Self {
+impl Player {
+    pub fn new(texture_map: &'static Texture) -> Self {
         Player {
+            index: 0,
             state: PlayerState::Idle,
             direction: Direction::None,
             hp: 100,
             max_hp: 100,
             velocity: 0,
-            pos: PlayerPos { x: None, y: None },
-            texture_map,
-            width: 32,
+            body: MobBody::new(texture_map, 32),
+            invulnerable_frames: 0,
+            knockback: (0, 0),
+            knockback_frames: 0,
+            firing: false,
+            fire_cooldown: 0,
         }
     }
 
-    fn draw<'d, SPI, DC>(
-        &mut self,
-        x: u16,
-        y: u16,
-        display: &mut mipidsi::Display<
-            mipidsi::interface::SpiInterface<'d, SPI, DC>,
-            mipidsi::models::ILI9341Rgb565,
-            mipidsi::NoResetPin,
-        >,
-    ) where
-        SPI: embedded_hal::spi::SpiDevice,
-        DC: embedded_hal::digital::OutputPin,
-    {
-        let offset: u16 = self.width / 2;
-
-        if let Err(_) = display.set_pixels(
-            x - offset,
-            y - offset,
-            (x + self.width) - offset,
-            (y + self.width) - offset,
-            self.texture_map.clone(),
-        ) {
-            error!("Could not draw player");
+    pub fn with_index(mut self, index: u8) -> Self {
+        self.index = index;
+        self
+    }
+
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn hp(&self) -> u8 {
+        self.hp
+    }
+
+    pub fn max_hp(&self) -> u8 {
+        self.max_hp
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_frames > 0
+    }
+
+    // Returns false when the hit was ignored because of invulnerability frames.
+    pub fn damage(&mut self, amount: u8, source: Aabb) -> bool {
+        if self.hp == 0 || self.is_invulnerable() {
+            return false;
+        }
+
+        self.hp = self.hp.saturating_sub(amount);
+        self.invulnerable_frames = INVULNERABLE_FRAMES;
+
+        // Shove the player away from whatever hit it, along the dominant axis.
+        let (x, y) = self.bounds().center();
+        let (sx, sy) = source.center();
+        self.knockback = if (x - sx).abs() >= (y - sy).abs() {
+            ((x - sx).signum() * KNOCKBACK_VELOCITY, 0)
+        } else {
+            (0, (y - sy).signum() * KNOCKBACK_VELOCITY)
+        };
+        self.knockback_frames = KNOCKBACK_FRAMES;
+
+        true
+    }
+
+    pub fn heal(&mut self, amount: u8) {
+        if self.hp == 0 {
+            return;
+        }
+        self.hp = self.hp.saturating_add(amount).min(self.max_hp);
+    }
+
+    // Direction of the next projectile if the fire button is held and the cooldown is over.
+    pub fn take_shot(&mut self) -> Option<Direction> {
+        if !self.firing || self.fire_cooldown > 0 || self.direction == Direction::None {
+            return None;
+        }
+        self.fire_cooldown = FIRE_COOLDOWN_FRAMES;
+        Some(self.direction)
+    }
+
+    fn is_visible(&self) -> bool {
+        (self.invulnerable_frames / BLINK_FRAMES).is_multiple_of(2)
+    }
+}
+
+impl Controllable for Player {
+    fn handle_input(&mut self, input: (u8, String)) {
+        self.firing = input.0 == NUMPAD_BUTTON_A;
+
+        let direction = match input.0 {
+            NUMPAD_UP => Direction::Up,
+            NUMPAD_DOWN => Direction::Down,
+            NUMPAD_LEFT => Direction::Left,
+            NUMPAD_RIGHT => Direction::Right,
+            _ => Direction::None,
+        };
+
+        if direction == Direction::None {
+            self.state = PlayerState::Idle;
+        } else {
+            self.state = PlayerState::Moving;
+            self.direction = direction;
+        }
+    }
+}
+
+impl Mob for Player {
+    fn body(&self) -> &MobBody {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut MobBody {
+        &mut self.body
+    }
+
+    fn is_alive(&self) -> bool {
+        self.hp > 0
+    }
+
+    fn current_effect(&self) -> ColorEffect {
+        if self.invulnerable_frames + HIT_FLASH_FRAMES > INVULNERABLE_FRAMES {
+            self.body.effect.with_flash(HIT_FLASH_PERCENT)
+        } else {
+            self.body.effect
         }
-        self.pos.x.replace(x);
-        self.pos.y.replace(y);
     }
 
     fn update_state<'d, SPI, DC>(
         &mut self,
-        input: (u8, alloc::string::String),
-        display: &mut mipidsi::Display<
-            mipidsi::interface::SpiInterface<'d, SPI, DC>,
-            mipidsi::models::ILI9341Rgb565,
-            mipidsi::NoResetPin,
-        >,
-    ) where
+        tiles: &impl SolidTiles,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
         SPI: embedded_hal::spi::SpiDevice,
         DC: embedded_hal::digital::OutputPin,
     {
-        if input.0 != NUMPAD_IDLE
-            || input.0 != NUMPAD_SELECT
-            || input.0 != NUMPAD_START
-            || input.0 != NUMPAD_BUTTON_A
-            || input.0 != NUMPAD_BUTTON_B
-        {
-            self.state = PlayerState::Moving;
-        }
-        if input.0 == NUMPAD_IDLE {
-            self.state = PlayerState::Idle;
+        if self.hp == 0 {
+            return Ok(());
         }
 
-        match self.state {
+        let (mut dx, mut dy) = match self.state {
             PlayerState::Idle => {
                 self.velocity = 0;
+                (0, 0)
             }
             PlayerState::Moving => {
                 self.velocity = 1;
-                let old_pos = self.pos.clone();
-
-                match input.0 {
-                    NUMPAD_UP => {
-                        self.direction = Direction::Up;
-                        self.pos
-                            .x
-                            .replace(self.pos.x.unwrap() + self.velocity as u16);
-                    }
-                    NUMPAD_DOWN => {
-                        self.direction = Direction::Down;
-                        self.pos
-                            .x
-                            .replace(self.pos.x.unwrap() - self.velocity as u16);
-                    }
-                    NUMPAD_LEFT => {
-                        self.direction = Direction::Left;
-                        self.pos
-                            .y
-                            .replace(self.pos.y.unwrap() - self.velocity as u16);
-                    }
-                    NUMPAD_RIGHT => {
-                        self.direction = Direction::Right;
-                        self.pos
-                            .y
-                            .replace(self.pos.y.unwrap() + self.velocity as u16);
-                    }
-                    _ => {}
-                }
-
-                self.draw_and_clean_dirty_pixels(
-                    old_pos,
-                    self.pos.x.unwrap() as u16,
-                    self.pos.y.unwrap() as u16,
-                    display,
-                );
+                self.direction.delta(self.velocity)
             }
-        }
-    }
+        };
 
-    fn draw_and_clean_dirty_pixels<'d, SPI, DC>(
-        &mut self,
-        old_pos: PlayerPos,
-        x: u16,
-        y: u16,
-        display: &mut mipidsi::Display<
-            mipidsi::interface::SpiInterface<'d, SPI, DC>,
-            mipidsi::models::ILI9341Rgb565,
-            mipidsi::NoResetPin,
-        >,
-    ) where
-        SPI: embedded_hal::spi::SpiDevice,
-        DC: embedded_hal::digital::OutputPin,
-    {
-        let offset: u16 = self.width / 2;
-
-        if let Err(_) = display.set_pixels(
-            x - offset,
-            y - offset,
-            (x + self.width) - offset,
-            (y + self.width) - offset,
-            self.texture_map.clone(),
-        ) {
-            error!("Could not draw player");
+        if self.knockback_frames > 0 {
+            self.knockback_frames -= 1;
+            dx += self.knockback.0;
+            dy += self.knockback.1;
         }
 
-        let mut colors_to_replace_dirty_pixels =
-            vec_into_psram::<Rgb565>(self.width as usize).unwrap();
-        for _ in 0..self.width {
-            if let Err(color) =
-                colors_to_replace_dirty_pixels.push_within_capacity(Rgb565::CSS_LIGHT_GREEN)
-            {
-                colors_to_replace_dirty_pixels.reserve_exact(1);
-
-                colors_to_replace_dirty_pixels
-                    .push_within_capacity(color)
-                    .unwrap();
-            }
-        }
+        self.fire_cooldown = self.fire_cooldown.saturating_sub(1);
 
-        match self.direction {
-            Direction::Up => {
-                if let Err(_) = display.set_pixels(
-                    old_pos.x.unwrap(),
-                    old_pos.y.unwrap() - offset,
-                    old_pos.x.unwrap() - offset,
-                    old_pos.y.unwrap() + self.width,
-                    colors_to_replace_dirty_pixels,
-                ) {
-                    error!("Could clean dirty pixels (UP)");
-                }
-            }
-            Direction::Left => {
-                if let Err(_) = display.set_pixels(
-                    old_pos.x.unwrap() - offset,
-                    old_pos.y.unwrap() + self.width - offset,
-                    old_pos.x.unwrap() + offset,
-                    old_pos.y.unwrap() + self.width - offset,
-                    colors_to_replace_dirty_pixels,
-                ) {
-                    error!("Could clean dirty pixels (LEFT)");
-                }
-            }
-            Direction::Right => {
-                if let Err(_) = display.set_pixels(
-                    old_pos.x.unwrap() - offset,
-                    old_pos.y.unwrap() - offset,
-                    old_pos.x.unwrap() + self.width,
-                    old_pos.y.unwrap(),
-                    colors_to_replace_dirty_pixels,
-                ) {
-                    error!("Could clean dirty pixels (RIGHT)");
-                }
-            }
-            Direction::Down => {
-                if let Err(_) = display.set_pixels(
-                    old_pos.x.unwrap() + self.width - offset,
-                    old_pos.y.unwrap() - offset,
-                    old_pos.x.unwrap() + self.width - offset,
-                    old_pos.y.unwrap() + self.width - offset,
-                    colors_to_replace_dirty_pixels,
-                ) {
-                    error!("Could clean dirty pixels (DOWN)");
-                }
-            }
-            Direction::None => {}
+        let was_visible = self.is_visible();
+        self.invulnerable_frames = self.invulnerable_frames.saturating_sub(1);
+        let visible = self.is_visible();
+
+        let old_pos = self.body.pos;
+        let old_bounds = self.bounds();
+        let (bounds, _) = move_and_slide(old_bounds, dx, dy, tiles);
+        if bounds == old_bounds
+            && visible == was_visible
+            && self.current_effect() == self.body.drawn_effect
+        {
+            return Ok(());
         }
 
-        self.pos.x.replace(x);
-        self.pos.y.replace(y);
+        let (x, y) = bounds.center();
+        if visible {
+            self.draw_and_clean_dirty_pixels(old_pos, x as u16, y as u16, camera, display)
+        } else {
+            self.body.pos.x.replace(x as u16);
+            self.body.pos.y.replace(y as u16);
+            fill_background(old_bounds, camera, display)
+        }
     }
 }
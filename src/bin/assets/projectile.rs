@@ -0,0 +1,89 @@
+use crate::{
+    camera::Camera,
+    collision::{SolidTiles, move_and_slide},
+    lcd::{DisplayError, LcdDisplay},
+};
+
+use super::{Direction, Mob, MobBody, Texture};
+
+pub const PROJECTILE_SIZE: u16 = 8;
+const PROJECTILE_LIFETIME: u16 = 120;
+
+pub struct Projectile {
+    direction: Direction,
+    velocity: u8,
+    lifetime: u16,
+    damage: u8,
+    body: MobBody,
+}
+
+impl Projectile {
+    pub fn new(texture_map: &'static Texture, direction: Direction) -> Self {
+        Projectile {
+            direction,
+            velocity: 2,
+            lifetime: PROJECTILE_LIFETIME,
+            damage: 10,
+            body: MobBody::new(texture_map, PROJECTILE_SIZE),
+        }
+    }
+
+    pub fn damage(&self) -> u8 {
+        self.damage
+    }
+
+    // Re-arms a pooled projectile.
+    pub fn reset(&mut self, direction: Direction, x: u16, y: u16) {
+        self.direction = direction;
+        self.lifetime = PROJECTILE_LIFETIME;
+        self.body.pos.x.replace(x);
+        self.body.pos.y.replace(y);
+    }
+
+    // Used once the projectile hits something so it gets despawned this frame.
+    pub fn expire(&mut self) {
+        self.lifetime = 0;
+    }
+}
+
+impl Mob for Projectile {
+    fn body(&self) -> &MobBody {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut MobBody {
+        &mut self.body
+    }
+
+    fn is_alive(&self) -> bool {
+        self.lifetime > 0
+    }
+
+    fn update_state<'d, SPI, DC>(
+        &mut self,
+        tiles: &impl SolidTiles,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        if self.lifetime == 0 {
+            return Ok(());
+        }
+        self.lifetime -= 1;
+
+        let old_pos = self.body.pos;
+        let (dx, dy) = self.direction.delta(self.velocity);
+        let (bounds, collision) = move_and_slide(self.bounds(), dx, dy, tiles);
+
+        if collision.any() {
+            self.expire();
+            return Ok(());
+        }
+
+        let (x, y) = bounds.center();
+        self.draw_and_clean_dirty_pixels(old_pos, x as u16, y as u16, camera, display)
+    }
+}
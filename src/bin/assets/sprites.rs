@@ -0,0 +1,45 @@
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::{RgbColor, WebColors};
+use log::{error, info};
+
+use super::{PROJECTILE_SIZE, Texture};
+use crate::pack::{AssetPack, PackError};
+use crate::utils::AllocError;
+
+const MOB_SIZE: u16 = 32;
+const PICKUP_SIZE: u16 = 16;
+
+// Textures of everything the game spawns. They are loaded once at boot and leaked, every
+// mob only keeps a reference to its texture.
+pub struct Sprites {
+    pub player: Texture,
+    pub enemy_patrol: Texture,
+    pub enemy_guard: Texture,
+    pub heal: Texture,
+    pub coin: Texture,
+    pub projectile: Texture,
+}
+
+impl Sprites {
+    // Takes whatever the pack has, sprites it lacks get solid placeholders.
+    pub fn load(pack: Option<&AssetPack<'static>>) -> Result<Self, AllocError> {
+        let sprite = |name: &str, size: u16, placeholder: Rgb565| {
+            match pack.map(|pack| pack.sprite(name, size, size)) {
+                Some(Ok(sprite)) => return Texture::from_sprite(&sprite),
+                Some(Err(PackError::NotFound)) => info!("Sprite {} is not in the asset pack", name),
+                Some(Err(e)) => error!("Could not load sprite {}: {:?}", name, e),
+                None => {}
+            }
+            Texture::solid(placeholder, size)
+        };
+
+        Ok(Sprites {
+            player: sprite("player", MOB_SIZE, Rgb565::RED)?,
+            enemy_patrol: sprite("enemy_patrol", MOB_SIZE, Rgb565::CSS_PURPLE)?,
+            enemy_guard: sprite("enemy_guard", MOB_SIZE, Rgb565::CSS_DARK_ORANGE)?,
+            heal: sprite("heal", PICKUP_SIZE, Rgb565::CSS_HOT_PINK)?,
+            coin: sprite("coin", PICKUP_SIZE, Rgb565::YELLOW)?,
+            projectile: sprite("projectile", PROJECTILE_SIZE, Rgb565::BLACK)?,
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::vec::Vec;
+use core::ops::Deref;
+use embedded_graphics::pixelcolor::{Rgb565, raw::RawU16};
+use embedded_graphics::prelude::RawData;
+
+use super::ColorEffect;
+use crate::pack::{Pixels, Sprite, SpriteEncoding};
+use crate::utils::{AllocError, Psram, Region};
+
+// Pixels of a square sprite in panel order, kept encoded the way the asset pack stores
+// them. They are decoded one by one while being sent, straight into the SPI buffer.
+pub struct Texture {
+    size: u16,
+    encoding: SpriteEncoding,
+    palette: Vec<u16, Psram>,
+    data: Data,
+}
+
+// Sprites from the pack are read straight out of the mapped partition, generated ones
+// live in PSRAM.
+enum Data {
+    Mapped(&'static [u8]),
+    Owned(Vec<u8, Psram>),
+}
+
+impl Deref for Data {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Data::Mapped(data) => data,
+            Data::Owned(data) => data,
+        }
+    }
+}
+
+impl Texture {
+    // A single run length encoded color, a handful of bytes whatever the size.
+    pub fn solid(color: Rgb565, size: u16) -> Result<Self, AllocError> {
+        let mut pixels = size as usize * size as usize;
+        let mut data = Psram.vec_from_elem(0u8, pixels.div_ceil(u8::MAX as usize) * 2)?;
+        for run in data.chunks_exact_mut(2) {
+            let len = pixels.min(u8::MAX as usize);
+            run[0] = len as u8;
+            pixels -= len;
+        }
+
+        Ok(Texture {
+            size,
+            encoding: SpriteEncoding::Rle,
+            palette: Psram.vec_from_elem(RawU16::from(color).into_inner(), 1)?,
+            data: Data::Owned(data),
+        })
+    }
+
+    // `sprite` has to be square and checked, like the ones `AssetPack::sprite` returns.
+    pub fn from_sprite(sprite: &Sprite<'static>) -> Result<Self, AllocError> {
+        Ok(Texture {
+            size: sprite.width,
+            encoding: sprite.encoding,
+            palette: Psram.vec_from_slice(&sprite.palette)?,
+            data: Data::Mapped(sprite.data),
+        })
+    }
+
+    // Decodes with `effect` applied. Textures with a palette only get their palette
+    // transformed, into `palette`, so the effect costs the same whatever the size.
+    pub fn pixels<'a>(
+        &'a self,
+        effect: &'a ColorEffect,
+        palette: &'a mut [u16; 256],
+    ) -> impl Iterator<Item = Rgb565> + 'a {
+        let palette = &mut palette[..self.palette.len()];
+        for (to, &from) in palette.iter_mut().zip(self.palette.iter()) {
+            *to = effect.apply_raw(from);
+        }
+        let per_pixel = self.encoding == SpriteEncoding::Raw && !effect.is_none();
+
+        let len = self.size as usize * self.size as usize;
+        Pixels::new(self.encoding, palette, &self.data, len).map(move |raw| {
+            let raw = if per_pixel {
+                effect.apply_raw(raw)
+            } else {
+                raw
+            };
+            Rgb565::from(RawU16::new(raw))
+        })
+    }
+}
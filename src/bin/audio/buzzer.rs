@@ -0,0 +1,86 @@
+use esp_hal::{
+    gpio::{AnyPin, DriveMode},
+    ledc::{
+        LowSpeed,
+        channel::{self, Channel, ChannelIFace},
+        timer::{self, Timer, TimerIFace},
+    },
+    time::Rate,
+};
+use log::error;
+
+use super::{AudioBackend, Tone};
+
+// Lowest pitch the timer divisor allows with 10 bit duty from the APB clock.
+const MIN_FREQUENCY: u16 = 80;
+
+// Passive piezo buzzer on a LEDC channel with a timer of its own, which gets retuned
+// for every note. One voice, the engine arpeggiates when more notes are playing.
+pub struct Buzzer<'a> {
+    timer: Timer<'a, LowSpeed>,
+    pin: AnyPin<'a>,
+    number: channel::Number,
+    playing: Option<Tone>,
+}
+
+impl<'a> Buzzer<'a> {
+    pub fn new(timer: Timer<'a, LowSpeed>, number: channel::Number, pin: AnyPin<'a>) -> Self {
+        let mut buzzer = Buzzer {
+            timer,
+            pin,
+            number,
+            playing: Some(Tone {
+                frequency: 0,
+                volume: 0,
+            }),
+        };
+        buzzer.set_voice(0, None);
+        buzzer
+    }
+
+    fn play(&mut self, tone: Option<Tone>) -> Result<(), &'static str> {
+        let (frequency, duty) = match tone {
+            // A square wave is loudest at half duty.
+            Some(tone) => (tone.frequency.max(MIN_FREQUENCY), tone.volume.min(100) / 2),
+            None => (MIN_FREQUENCY, 0),
+        };
+
+        if duty > 0 || !self.timer.is_configured() {
+            self.timer
+                .configure(timer::config::Config {
+                    duty: timer::config::Duty::Duty10Bit,
+                    clock_source: timer::LSClockSource::APBClk,
+                    frequency: Rate::from_hz(frequency as u32),
+                })
+                .map_err(|_| "timer")?;
+        }
+
+        // The LEDC keeps running once configured, so a short lived channel is enough.
+        let mut channel = Channel::<LowSpeed>::new(self.number, self.pin.reborrow());
+        channel
+            .configure(channel::config::Config {
+                timer: &self.timer,
+                duty_pct: duty,
+                drive_mode: DriveMode::PushPull,
+            })
+            .map_err(|_| "channel")
+    }
+}
+
+impl AudioBackend for Buzzer<'_> {
+    fn voices(&self) -> usize {
+        1
+    }
+
+    fn set_voice(&mut self, voice: usize, tone: Option<Tone>) {
+        if voice != 0 || tone == self.playing {
+            return;
+        }
+
+        if let Err(part) = self.play(tone) {
+            error!("Could not configure buzzer {}", part);
+            return;
+        }
+        self.playing = tone;
+    }
+}
@@ -0,0 +1,223 @@
+// Sound engine. Everything but the buzzer backend is plain `core`/`alloc` code so the
+// host tools can include this module and render the same music to WAV files.
+
+pub mod notes;
+mod tracks;
+pub use tracks::{Sfx, THEME};
+
+#[cfg(target_os = "none")]
+mod buzzer;
+#[cfg(target_os = "none")]
+pub use buzzer::Buzzer;
+
+#[cfg(not(target_os = "none"))]
+mod wav;
+#[cfg(not(target_os = "none"))]
+pub use wav::WavWriter;
+
+pub const MUSIC_CHANNELS: usize = 2;
+// Effects get the last channel, it wins over music when voices run short.
+const SFX_CHANNEL: usize = MUSIC_CHANNELS;
+const CHANNELS: usize = MUSIC_CHANNELS + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tone {
+    pub frequency: u16,
+    // Percent.
+    pub volume: u8,
+}
+
+pub trait AudioBackend {
+    // How many tones can sound at the same time.
+    fn voices(&self) -> usize;
+
+    // `None` silences the voice.
+    fn set_voice(&mut self, voice: usize, tone: Option<Tone>);
+
+    // Lets time pass. Hardware keeps playing on its own, offline backends render here.
+    fn advance(&mut self, _ms: u32) {}
+}
+
+// One tracker row: a note, or a rest when `frequency` is 0, held for `rows` rows.
+#[derive(Clone, Copy, Debug)]
+pub struct Step {
+    pub frequency: u16,
+    pub rows: u8,
+}
+
+pub const fn note(frequency: u16, rows: u8) -> Step {
+    Step { frequency, rows }
+}
+
+pub const fn rest(rows: u8) -> Step {
+    Step { frequency: 0, rows }
+}
+
+pub struct Pattern {
+    pub steps: &'static [Step],
+    pub row_ms: u16,
+    pub volume: u8,
+}
+
+// A music track is one pattern per music channel, played in lockstep and looped.
+pub type Track = [&'static Pattern; MUSIC_CHANNELS];
+
+#[derive(Clone, Copy)]
+struct Channel {
+    pattern: Option<&'static Pattern>,
+    looping: bool,
+    step: usize,
+    elapsed_ms: u32,
+}
+
+impl Channel {
+    const IDLE: Channel = Channel {
+        pattern: None,
+        looping: false,
+        step: 0,
+        elapsed_ms: 0,
+    };
+
+    fn start(&mut self, pattern: &'static Pattern, looping: bool) {
+        *self = Channel {
+            pattern: Some(pattern),
+            looping,
+            step: 0,
+            elapsed_ms: 0,
+        };
+    }
+
+    fn tone(&self) -> Option<Tone> {
+        let pattern = self.pattern?;
+        let step = pattern.steps.get(self.step)?;
+        if step.frequency == 0 {
+            return None;
+        }
+        Some(Tone {
+            frequency: step.frequency,
+            volume: pattern.volume,
+        })
+    }
+
+    fn advance(&mut self, ms: u32) {
+        let Some(pattern) = self.pattern else {
+            return;
+        };
+        self.elapsed_ms += ms;
+
+        // Steps without rows take no time. A whole pass of them would loop forever, so the
+        // channel stops instead.
+        let mut empty_steps = 0;
+        while let Some(step) = pattern.steps.get(self.step) {
+            let length = step.rows as u32 * pattern.row_ms as u32;
+            if self.elapsed_ms < length {
+                return;
+            }
+            self.elapsed_ms -= length;
+            self.step += 1;
+
+            empty_steps = if length == 0 { empty_steps + 1 } else { 0 };
+            if empty_steps >= pattern.steps.len() {
+                self.pattern = None;
+                return;
+            }
+
+            if self.step == pattern.steps.len() {
+                if !self.looping {
+                    self.pattern = None;
+                    return;
+                }
+                self.step = 0;
+            }
+        }
+    }
+}
+
+pub struct Audio<B: AudioBackend> {
+    backend: B,
+    channels: [Channel; CHANNELS],
+    volume: u8,
+    // Rotates the last voice between channels that did not get one of their own.
+    arpeggio: usize,
+}
+
+impl<B: AudioBackend> Audio<B> {
+    pub fn new(backend: B) -> Self {
+        Audio {
+            backend,
+            channels: [Channel::IDLE; CHANNELS],
+            volume: 100,
+            arpeggio: 0,
+        }
+    }
+
+    // For the host tools, which read back what the `wav` backend recorded.
+    #[cfg(not(target_os = "none"))]
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    #[cfg(not(target_os = "none"))]
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+    }
+
+    pub fn play_music(&mut self, track: &Track) {
+        for (channel, pattern) in self.channels.iter_mut().zip(track) {
+            channel.start(pattern, true);
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        for channel in self.channels[..MUSIC_CHANNELS].iter_mut() {
+            *channel = Channel::IDLE;
+        }
+    }
+
+    // One-shot, cuts off whatever effect is still playing.
+    pub fn play_effect(&mut self, sfx: Sfx) {
+        self.channels[SFX_CHANNEL].start(sfx.pattern(), false);
+    }
+
+    // Call once per frame with the time since the last call.
+    pub fn update(&mut self, ms: u32) {
+        for channel in self.channels.iter_mut() {
+            channel.advance(ms);
+        }
+
+        // Effects first, then music in channel order.
+        let mut active = [None; CHANNELS];
+        let mut count = 0;
+        for channel in [SFX_CHANNEL].into_iter().chain(0..MUSIC_CHANNELS) {
+            if let Some(tone) = self.channels[channel].tone() {
+                active[count] = Some(Tone {
+                    frequency: tone.frequency,
+                    volume: (tone.volume as u16 * self.volume as u16 / 100) as u8,
+                });
+                count += 1;
+            }
+        }
+
+        let voices = self.backend.voices();
+        for voice in 0..voices {
+            let tone = if count <= voices || voice + 1 < voices {
+                active.get(voice).copied().flatten()
+            } else {
+                let rest = &active[voice..count];
+                rest[self.arpeggio % rest.len()]
+            };
+            self.backend.set_voice(voice, tone);
+        }
+        self.arpeggio = self.arpeggio.wrapping_add(1);
+
+        self.backend.advance(ms);
+    }
+}
@@ -0,0 +1,140 @@
+use super::{Pattern, Step, Track, note, notes::*, rest};
+
+const THEME_ROW_MS: u16 = 150;
+
+const THEME_LEAD: Pattern = Pattern {
+    steps: &[
+        note(E5, 2),
+        note(G5, 2),
+        note(A5, 2),
+        note(G5, 1),
+        note(E5, 1),
+        note(D5, 2),
+        note(E5, 2),
+        rest(4),
+        note(C5, 2),
+        note(D5, 2),
+        note(E5, 2),
+        note(G5, 2),
+        note(A5, 4),
+        rest(4),
+        note(A5, 2),
+        note(C6, 2),
+        note(B5, 2),
+        note(A5, 1),
+        note(G5, 1),
+        note(E5, 2),
+        note(D5, 2),
+        rest(4),
+        note(D5, 2),
+        note(E5, 2),
+        note(D5, 2),
+        note(B4, 2),
+        note(C5, 4),
+        rest(4),
+    ],
+    row_ms: THEME_ROW_MS,
+    volume: 60,
+};
+
+const THEME_BASS: Pattern = Pattern {
+    steps: &[
+        note(A3, 2),
+        rest(2),
+        note(A3, 2),
+        note(E3, 2),
+        note(A3, 2),
+        rest(2),
+        note(G3, 2),
+        note(E3, 2),
+        note(F3, 2),
+        rest(2),
+        note(F3, 2),
+        note(C4, 2),
+        note(F3, 2),
+        rest(2),
+        note(E3, 2),
+        note(G3, 2),
+        note(A3, 2),
+        rest(2),
+        note(A3, 2),
+        note(E3, 2),
+        note(A3, 2),
+        rest(2),
+        note(G3, 2),
+        note(E3, 2),
+        note(G3, 2),
+        rest(2),
+        note(G3, 2),
+        note(D4, 2),
+        note(C4, 4),
+        rest(4),
+    ],
+    row_ms: THEME_ROW_MS,
+    volume: 40,
+};
+
+pub const THEME: Track = [&THEME_LEAD, &THEME_BASS];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sfx {
+    Shoot,
+    Hurt,
+    Heal,
+    Coin,
+    EnemyKilled,
+    GameOver,
+}
+
+impl Sfx {
+    pub fn pattern(self) -> &'static Pattern {
+        match self {
+            Sfx::Shoot => &SHOOT,
+            Sfx::Hurt => &HURT,
+            Sfx::Heal => &HEAL,
+            Sfx::Coin => &COIN,
+            Sfx::EnemyKilled => &ENEMY_KILLED,
+            Sfx::GameOver => &GAME_OVER,
+        }
+    }
+}
+
+const fn effect(steps: &'static [Step], row_ms: u16) -> Pattern {
+    Pattern {
+        steps,
+        row_ms,
+        volume: 80,
+    }
+}
+
+const SHOOT: Pattern = effect(&[note(C6, 1), note(G5, 1), note(D5, 1)], 20);
+const HURT: Pattern = effect(&[note(C4, 2), note(GS3, 2), note(E3, 3)], 30);
+const HEAL: Pattern = effect(&[note(C5, 1), note(E5, 1), note(G5, 1), note(C6, 3)], 40);
+const COIN: Pattern = effect(&[note(B5, 2), note(E6, 6)], 30);
+const ENEMY_KILLED: Pattern = effect(
+    &[
+        note(G4, 1),
+        note(C5, 1),
+        note(G4, 1),
+        note(C4, 1),
+        note(G3, 3),
+    ],
+    30,
+);
+const GAME_OVER: Pattern = effect(
+    &[
+        note(C5, 3),
+        note(G4, 3),
+        note(E4, 3),
+        note(A4, 2),
+        note(B4, 2),
+        note(A4, 2),
+        note(GS4, 2),
+        note(AS4, 2),
+        note(GS4, 2),
+        note(G4, 2),
+        note(F4, 2),
+        note(G4, 6),
+    ],
+    60,
+);
@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+
+use super::{AudioBackend, Tone};
+
+// Renders square waves into 8 bit mono PCM, so music can be listened to off the device.
+pub struct WavWriter {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    samples: Vec<u8>,
+    // Milliseconds that did not make a whole sample yet, in 1/1000 samples.
+    remainder: u32,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Voice {
+    tone: Option<Tone>,
+    // Position in the period, in 1/sample_rate cycles.
+    phase: u32,
+}
+
+impl WavWriter {
+    pub fn new(sample_rate: u32, voices: usize) -> Self {
+        WavWriter {
+            sample_rate,
+            voices: alloc::vec![Voice::default(); voices.max(1)],
+            samples: Vec::new(),
+            remainder: 0,
+        }
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.samples.len() as u64 * 1000 / self.sample_rate as u64
+    }
+
+    fn sample(&mut self) -> u8 {
+        let voices = self.voices.len() as i32;
+        let mut level = 0i32;
+        for voice in self.voices.iter_mut() {
+            let Some(tone) = voice.tone else {
+                continue;
+            };
+
+            voice.phase = (voice.phase + tone.frequency as u32) % self.sample_rate;
+            let amplitude = tone.volume.min(100) as i32 * 127 / 100 / voices;
+            level += if voice.phase < self.sample_rate / 2 {
+                amplitude
+            } else {
+                -amplitude
+            };
+        }
+        (128 + level.clamp(-128, 127)) as u8
+    }
+
+    // RIFF container around everything rendered so far.
+    pub fn into_wav(self) -> Vec<u8> {
+        let data_len = self.samples.len() as u32;
+        let mut wav = Vec::with_capacity(44 + self.samples.len());
+
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, mono.
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        // Byte rate and block align for one byte per sample.
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8u16.to_le_bytes());
+
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend_from_slice(&self.samples);
+        wav
+    }
+}
+
+impl AudioBackend for WavWriter {
+    fn voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    fn set_voice(&mut self, voice: usize, tone: Option<Tone>) {
+        if let Some(voice) = self.voices.get_mut(voice) {
+            voice.tone = tone;
+        }
+    }
+
+    fn advance(&mut self, ms: u32) {
+        let total = self.remainder + ms * self.sample_rate;
+        self.remainder = total % 1000;
+
+        for _ in 0..total / 1000 {
+            let sample = self.sample();
+            self.samples.push(sample);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use embedded_graphics::pixelcolor::Rgb565;
+
+use crate::{
+    collision::{Aabb, TILE_SIZE},
+    lcd::{DisplayError, LcdDisplay, LcdMonitor, Screen, Scroll, ScrollArea},
+};
+
+// How close the target gets to the top or bottom of the screen before the camera jumps.
+const JUMP_MARGIN: i32 = TILE_SIZE as i32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMove {
+    None,
+    // Moved along the scroll axis, only this strip of the world is new on screen.
+    Scrolled(Aabb),
+    // Everything in view has to be redrawn.
+    Jumped,
+}
+
+// Which part of the world is on screen, world coordinates use the panel axes like
+// `collision`.
+//
+// The panel scrolls along panel y in hardware, so the view along it is the scroll
+// area and world row `y` always lives in the same panel row, `y % lines` into the area.
+// The scroll offset is set so the view starts at the first line, following the target
+// left and right then only needs the strip that came into view drawn. Panel x cannot
+// scroll, moving the view up or down means a full redraw, so that only happens when the
+// target gets close to the edge.
+//
+// Panels that cannot scroll that way move along panel y like along panel x, by jumping.
+//
+// Screen fixed drawing like dialogs goes through `scroll()` so it stays in place. The HUD
+// is in the fixed area at the start of the scroll axis, the view begins after it.
+pub struct Camera {
+    x: i32,
+    y: i32,
+    world: Aabb,
+    area: ScrollArea,
+    // The play field across the scroll axis, the whole screen height. Along it the view
+    // is the scroll area.
+    view_width: u16,
+    hardware_scroll: bool,
+}
+
+impl Camera {
+    // `area` has to be what the display got in `LcdMonitor::set_scroll_area`.
+    pub fn new(screen: Screen, world: Aabb, area: ScrollArea) -> Self {
+        Camera {
+            x: 0,
+            y: 0,
+            world,
+            area,
+            view_width: screen.height(),
+            hardware_scroll: true,
+        }
+    }
+
+    pub fn with_hardware_scroll(mut self, enabled: bool) -> Self {
+        self.hardware_scroll = enabled;
+        self
+    }
+
+    pub fn view(&self) -> Aabb {
+        Aabb::new(self.x, self.y, self.view_width, self.area.lines())
+    }
+
+    pub fn scroll(&self) -> Scroll {
+        if !self.hardware_scroll {
+            return Scroll::new(self.area, 0);
+        }
+        Scroll::new(
+            self.area,
+            self.y.rem_euclid(self.area.lines() as i32) as u16,
+        )
+    }
+
+    // Where world `x`/`y` is on screen, in panel axes. `None` when it is out of view.
+    pub fn to_screen(&self, x: i32, y: i32) -> Option<(u16, u16)> {
+        if !self.view().intersects(&Aabb::new(x, y, 1, 1)) {
+            return None;
+        }
+        Some(((x - self.x) as u16, self.area.top() + (y - self.y) as u16))
+    }
+
+    // As much of `target` as `follow` can keep in view, cut down around `lead` when it is
+    // too big, e.g. for players that went separate ways. `lead` has to be inside `target`.
+    pub fn fit(&self, target: Aabb, lead: Aabb) -> Aabb {
+        let margin = 2 * JUMP_MARGIN as u16;
+        let (x, width) = fit_axis(
+            (target.left(), target.right()),
+            (lead.left(), lead.right()),
+            self.view_width.saturating_sub(margin),
+        );
+        let (y, height) = fit_axis(
+            (target.top(), target.bottom()),
+            (lead.top(), lead.bottom()),
+            self.area.lines().saturating_sub(margin),
+        );
+        Aabb::new(x, y, width, height)
+    }
+
+    // Keeps `target` centered left to right and in view top to bottom. Nothing is drawn,
+    // the caller repaints what the returned move says and then calls `apply`.
+    pub fn follow(&mut self, target: Aabb) -> CameraMove {
+        let (cx, cy) = target.center();
+        let lines = self.area.lines();
+
+        let x = jump(
+            self.x,
+            (target.left(), target.right()),
+            cx,
+            self.view_width,
+            self.world.width,
+        );
+        let y = if self.hardware_scroll {
+            clamp(cy - (lines / 2) as i32, lines, self.world.height)
+        } else {
+            jump(
+                self.y,
+                (target.top(), target.bottom()),
+                cy,
+                lines,
+                self.world.height,
+            )
+        };
+
+        let jumped = x != self.x || (y != self.y && !self.hardware_scroll);
+        let dy = y - self.y;
+        self.x = x;
+        self.y = y;
+
+        if jumped || dy.unsigned_abs() >= lines as u32 {
+            CameraMove::Jumped
+        } else if dy > 0 {
+            CameraMove::Scrolled(Aabb::new(
+                x,
+                y + lines as i32 - dy,
+                self.view_width,
+                dy as u16,
+            ))
+        } else if dy < 0 {
+            CameraMove::Scrolled(Aabb::new(x, y, self.view_width, (-dy) as u16))
+        } else {
+            CameraMove::None
+        }
+    }
+
+    // Scrolls the panel to the current view.
+    pub fn apply<'d, SPI, DC>(
+        &self,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        LcdMonitor::set_scroll_offset(display, self.scroll())
+    }
+
+    // Sends the part of `area` that is in view. `pixels` cover all of `area`, in panel
+    // order like a `Texture`.
+    pub fn draw<'d, SPI, DC>(
+        &self,
+        area: Aabb,
+        pixels: impl Iterator<Item = Rgb565>,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let Some(visible) = area.intersection(&self.view()) else {
+            return Ok(());
+        };
+        let Some((x, y)) = self.to_screen(visible.x, visible.y) else {
+            return Ok(());
+        };
+
+        // Rows run along panel y, each `area.width` pixels long.
+        let stride = area.width as usize;
+        let columns = (visible.x - area.x) as usize..(visible.right() - area.x) as usize;
+        let pixels = pixels
+            .skip((visible.y - area.y) as usize * stride)
+            .take(visible.height as usize * stride)
+            .enumerate()
+            .filter(move |(index, _)| columns.contains(&(index % stride)))
+            .map(|(_, color)| color);
+
+        LcdMonitor::set_pixels_scrolled(
+            display,
+            self.scroll(),
+            x,
+            y,
+            visible.width,
+            visible.height,
+            pixels,
+        )
+    }
+
+    pub fn fill<'d, SPI, DC>(
+        &self,
+        area: Aabb,
+        color: Rgb565,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let Some(visible) = area.intersection(&self.view()) else {
+            return Ok(());
+        };
+        let Some((x, y)) = self.to_screen(visible.x, visible.y) else {
+            return Ok(());
+        };
+
+        let len = visible.width as usize * visible.height as usize;
+        LcdMonitor::set_pixels_scrolled(
+            display,
+            self.scroll(),
+            x,
+            y,
+            visible.width,
+            visible.height,
+            core::iter::repeat_n(color, len),
+        )
+    }
+}
+
+// Start of the view along one axis that only moves once the target, from `from` to `to`
+// with its center at `center`, gets close to the edge.
+fn jump(start: i32, (from, to): (i32, i32), center: i32, view: u16, world: u16) -> i32 {
+    if from - start < JUMP_MARGIN || start + view as i32 - to < JUMP_MARGIN {
+        clamp(center - (view / 2) as i32, view, world)
+    } else {
+        start
+    }
+}
+
+// Start and length of at most `max` of `from..to` along one axis, centered on it where
+// the lead, `lead_from..lead_to`, allows.
+fn fit_axis((from, to): (i32, i32), (lead_from, lead_to): (i32, i32), max: u16) -> (i32, u16) {
+    let len = (to - from) as u16;
+    if len <= max {
+        return (from, len);
+    }
+    let centered = from + (len - max) as i32 / 2;
+    let start = centered.min(lead_from).max(lead_to - max as i32);
+    (start, max)
+}
+
+// Start of the view along one axis, kept inside the world. Worlds smaller than the view
+// stay at its start.
+fn clamp(start: i32, view: u16, world: u16) -> i32 {
+    start.min(world as i32 - view as i32).max(0)
+}
@@ -0,0 +1,246 @@
+// Everything in here is plain integer math with no esp-hal types, so it can be pulled
+// into a host crate for testing.
+//
+// Coordinates follow the panel: `x` runs along the screen height and `y` along its width,
+// the same axes `Player::draw` and `LcdMonitor` use. They are world coordinates, the
+// `Camera` maps them onto the panel.
+
+use alloc::vec::Vec;
+
+pub const TILE_SIZE: u16 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Aabb {
+    pub x: i32,
+    pub y: i32,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Collision {
+    pub x: bool,
+    pub y: bool,
+}
+
+impl Collision {
+    pub fn any(&self) -> bool {
+        self.x || self.y
+    }
+}
+
+impl Aabb {
+    pub const fn new(x: i32, y: i32, width: u16, height: u16) -> Self {
+        Aabb {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_center(cx: u16, cy: u16, width: u16, height: u16) -> Self {
+        Aabb {
+            x: cx as i32 - (width / 2) as i32,
+            y: cy as i32 - (height / 2) as i32,
+            width,
+            height,
+        }
+    }
+
+    pub fn left(&self) -> i32 {
+        self.x
+    }
+
+    pub fn top(&self) -> i32 {
+        self.y
+    }
+
+    // Exclusive edges
+    pub fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        (
+            self.x + (self.width / 2) as i32,
+            self.y + (self.height / 2) as i32,
+        )
+    }
+
+    pub fn translate(&self, dx: i32, dy: i32) -> Self {
+        Aabb {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }
+    }
+
+    pub fn expand(&self, by: u16) -> Self {
+        Aabb {
+            x: self.x - by as i32,
+            y: self.y - by as i32,
+            width: self.width + by * 2,
+            height: self.height + by * 2,
+        }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.left() < other.right()
+            && other.left() < self.right()
+            && self.top() < other.bottom()
+            && other.top() < self.bottom()
+    }
+
+    // Smallest box around both.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let (left, top) = (self.left().min(other.left()), self.top().min(other.top()));
+        let (right, bottom) = (
+            self.right().max(other.right()),
+            self.bottom().max(other.bottom()),
+        );
+        Aabb::new(left, top, (right - left) as u16, (bottom - top) as u16)
+    }
+
+    pub fn intersection(&self, other: &Aabb) -> Option<Aabb> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let (left, top) = (self.left().max(other.left()), self.top().max(other.top()));
+        let (right, bottom) = (
+            self.right().min(other.right()),
+            self.bottom().min(other.bottom()),
+        );
+        Some(Aabb::new(
+            left,
+            top,
+            (right - left) as u16,
+            (bottom - top) as u16,
+        ))
+    }
+}
+
+pub trait SolidTiles {
+    fn tile_size(&self) -> u16;
+
+    fn is_solid(&self, tx: i32, ty: i32) -> bool;
+}
+
+// The level, `tx` tiles along panel x by `ty` along panel y. It can be any size, the
+// `Camera` shows the part around the player.
+pub struct TileGrid {
+    tx: usize,
+    ty: usize,
+    solid: Vec<bool>,
+}
+
+impl TileGrid {
+    pub fn new(tx: usize, ty: usize) -> Self {
+        TileGrid {
+            tx,
+            ty,
+            solid: alloc::vec![false; tx * ty],
+        }
+    }
+
+    pub fn set_solid(&mut self, tx: usize, ty: usize, solid: bool) {
+        if tx < self.tx && ty < self.ty {
+            self.solid[tx * self.ty + ty] = solid;
+        }
+    }
+
+    // The whole level in world coordinates.
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(
+            0,
+            0,
+            (self.tx * TILE_SIZE as usize) as u16,
+            (self.ty * TILE_SIZE as usize) as u16,
+        )
+    }
+
+    pub fn solid_tiles(&self) -> impl Iterator<Item = Aabb> + '_ {
+        self.solid
+            .iter()
+            .enumerate()
+            .filter(|(_, solid)| **solid)
+            .map(|(index, _)| {
+                let (tx, ty) = (index / self.ty, index % self.ty);
+                Aabb::new(
+                    tx as i32 * TILE_SIZE as i32,
+                    ty as i32 * TILE_SIZE as i32,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                )
+            })
+    }
+}
+
+impl SolidTiles for TileGrid {
+    fn tile_size(&self) -> u16 {
+        TILE_SIZE
+    }
+
+    // Anything outside the grid counts as a wall so mobs can never leave the level.
+    fn is_solid(&self, tx: i32, ty: i32) -> bool {
+        if tx < 0 || ty < 0 || tx as usize >= self.tx || ty as usize >= self.ty {
+            return true;
+        }
+        self.solid[tx as usize * self.ty + ty as usize]
+    }
+}
+
+fn tile_range(start: i32, end: i32, tile_size: u16) -> (i32, i32) {
+    let size = tile_size as i32;
+    (start.div_euclid(size), (end - 1).div_euclid(size))
+}
+
+pub fn collides_with_tiles(aabb: &Aabb, tiles: &impl SolidTiles) -> bool {
+    let (tx_start, tx_end) = tile_range(aabb.left(), aabb.right(), tiles.tile_size());
+    let (ty_start, ty_end) = tile_range(aabb.top(), aabb.bottom(), tiles.tile_size());
+
+    for tx in tx_start..=tx_end {
+        for ty in ty_start..=ty_end {
+            if tiles.is_solid(tx, ty) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+pub fn collides_with_entity(aabb: &Aabb, other: &Aabb) -> bool {
+    aabb.intersects(other)
+}
+
+// Moves one pixel at a time so fast movers can't tunnel through a tile.
+fn step_axis(aabb: Aabb, delta: i32, horizontal: bool, tiles: &impl SolidTiles) -> (Aabb, bool) {
+    let step = delta.signum();
+    let mut moved = aabb;
+
+    for _ in 0..delta.abs() {
+        let next = if horizontal {
+            moved.translate(step, 0)
+        } else {
+            moved.translate(0, step)
+        };
+        if collides_with_tiles(&next, tiles) {
+            return (moved, true);
+        }
+        moved = next;
+    }
+    (moved, false)
+}
+
+/// Applies `dx` then `dy`, stopping each axis at the first solid tile so a mob pressed
+/// diagonally against a wall keeps sliding along it.
+pub fn move_and_slide(aabb: Aabb, dx: i32, dy: i32, tiles: &impl SolidTiles) -> (Aabb, Collision) {
+    let (aabb, hit_x) = step_axis(aabb, dx, true, tiles);
+    let (aabb, hit_y) = step_axis(aabb, dy, false, tiles);
+
+    (aabb, Collision { x: hit_x, y: hit_y })
+}
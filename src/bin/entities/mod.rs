@@ -0,0 +1,545 @@
+use alloc::{string::String, vec::Vec};
+use esp_alloc::MemoryCapability;
+use log::warn;
+
+use crate::{
+    assets::{
+        Controllable, Direction, Enemy, Mob, PROJECTILE_SIZE, Pickup, PickupKind, Player,
+        Projectile, Texture,
+    },
+    camera::Camera,
+    collision::{Aabb, SolidTiles, collides_with_entity, collides_with_tiles},
+    lcd::{BACKGROUND_COLOR, DisplayError, LcdDisplay},
+    utils::{AllocError, RegionVec},
+};
+
+pub enum Entity {
+    Player(Player),
+    Enemy(Enemy),
+    Projectile(Projectile),
+    Pickup(Pickup),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntityId {
+    index: u16,
+    generation: u16,
+}
+
+#[derive(Debug)]
+pub enum EntityError {
+    CapacityExhausted,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum GameEvent {
+    PickupCollected(PickupKind),
+    PlayerDamaged { player: u8, hp: u8 },
+    PlayerDied { player: u8 },
+    EnemyKilled,
+    ProjectileFired,
+}
+
+enum SlotState {
+    Free,
+    // Spawned this frame: not updated or drawn until the next `flush`.
+    Spawned,
+    Active,
+    // Despawned this frame: still holds the entity so `flush` can erase it.
+    Despawned,
+}
+
+struct Slot {
+    generation: u16,
+    state: SlotState,
+    entity: Option<Entity>,
+}
+
+// Fixed-capacity arena living in PSRAM. Spawning and despawning only flag slots, the
+// actual changes land in `flush` so the frame that is being updated never shifts.
+pub struct EntityManager {
+    slots: RegionVec<Slot>,
+    events: Vec<GameEvent>,
+    // Spare projectiles, so firing never allocates. Despawned ones come back here.
+    projectile_pool: RegionVec<Projectile>,
+}
+
+impl Entity {
+    pub fn bounds(&self) -> Aabb {
+        match self {
+            Entity::Player(player) => player.bounds(),
+            Entity::Enemy(enemy) => enemy.bounds(),
+            Entity::Projectile(projectile) => projectile.bounds(),
+            Entity::Pickup(pickup) => pickup.bounds(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        match self {
+            Entity::Player(player) => player.is_alive(),
+            Entity::Enemy(enemy) => enemy.is_alive(),
+            Entity::Projectile(projectile) => projectile.is_alive(),
+            Entity::Pickup(pickup) => pickup.is_alive(),
+        }
+    }
+
+    pub fn update_state<'d, SPI, DC>(
+        &mut self,
+        tiles: &impl SolidTiles,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        match self {
+            Entity::Player(player) => player.update_state(tiles, camera, display),
+            Entity::Enemy(enemy) => enemy.update_state(tiles, camera, display),
+            Entity::Projectile(projectile) => projectile.update_state(tiles, camera, display),
+            Entity::Pickup(pickup) => pickup.update_state(tiles, camera, display),
+        }
+    }
+
+    pub fn redraw<'d, SPI, DC>(
+        &mut self,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let (x, y) = self.bounds().center();
+        let (x, y) = (x as u16, y as u16);
+
+        match self {
+            Entity::Player(player) => player.draw(x, y, camera, display),
+            Entity::Enemy(enemy) => enemy.draw(x, y, camera, display),
+            Entity::Projectile(projectile) => projectile.draw(x, y, camera, display),
+            Entity::Pickup(pickup) => pickup.draw(x, y, camera, display),
+        }
+    }
+}
+
+impl EntityManager {
+    pub fn with_capacity(capacity: u16) -> Result<Self, AllocError> {
+        let mut slots = RegionVec::with_capacity(MemoryCapability::External, capacity as usize)?;
+
+        for _ in 0..capacity {
+            let slot = Slot {
+                generation: 0,
+                state: SlotState::Free,
+                entity: None,
+            };
+            slots.push_within_capacity(slot).ok();
+        }
+
+        Ok(EntityManager {
+            slots,
+            events: Vec::new(),
+            projectile_pool: RegionVec::new(MemoryCapability::External),
+        })
+    }
+
+    // The pooled projectiles all share `texture`.
+    pub fn with_projectile_pool(
+        mut self,
+        count: u16,
+        texture: &'static Texture,
+    ) -> Result<Self, AllocError> {
+        let mut pool = RegionVec::with_capacity(MemoryCapability::External, count as usize)?;
+
+        for _ in 0..count {
+            let projectile = Projectile::new(texture, Direction::None);
+            pool.push_within_capacity(projectile).ok();
+        }
+
+        self.projectile_pool = pool;
+        Ok(self)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| !matches!(slot.state, SlotState::Free))
+            .count()
+    }
+
+    pub fn spawn(&mut self, entity: Entity) -> Result<EntityId, EntityError> {
+        let Some((index, slot)) = self
+            .slots
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| matches!(slot.state, SlotState::Free))
+        else {
+            warn!("Entity capacity of {} exhausted", self.slots.len());
+            return Err(EntityError::CapacityExhausted);
+        };
+
+        slot.state = SlotState::Spawned;
+        slot.entity = Some(entity);
+
+        Ok(EntityId {
+            index: index as u16,
+            generation: slot.generation,
+        })
+    }
+
+    // Frees every slot right away without erasing anything, for when the whole scene gets
+    // repainted anyway. Projectiles go back to the pool.
+    pub fn reset(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if matches!(slot.state, SlotState::Free) {
+                continue;
+            }
+            slot.state = SlotState::Free;
+            slot.generation = slot.generation.wrapping_add(1);
+            if let Some(Entity::Projectile(projectile)) = slot.entity.take() {
+                self.projectile_pool.push_within_capacity(projectile).ok();
+            }
+        }
+        self.events.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| matches!(slot.state, SlotState::Active))
+            .filter_map(|(index, slot)| {
+                let id = EntityId {
+                    index: index as u16,
+                    generation: slot.generation,
+                };
+                slot.entity.as_ref().map(|entity| (id, entity))
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut Entity)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, slot)| matches!(slot.state, SlotState::Active))
+            .filter_map(|(index, slot)| {
+                let id = EntityId {
+                    index: index as u16,
+                    generation: slot.generation,
+                };
+                slot.entity.as_mut().map(|entity| (id, entity))
+            })
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &Player> {
+        self.iter().filter_map(|(_, entity)| match entity {
+            Entity::Player(player) => Some(player),
+            _ => None,
+        })
+    }
+
+    pub fn players_mut(&mut self) -> impl Iterator<Item = &mut Player> {
+        self.iter_mut().filter_map(|(_, entity)| match entity {
+            Entity::Player(player) => Some(player),
+            _ => None,
+        })
+    }
+
+    // The box around every player, what the camera keeps in view.
+    pub fn players_bounds(&self) -> Option<Aabb> {
+        self.players()
+            .map(|player| player.bounds())
+            .reduce(|all, bounds| all.union(&bounds))
+    }
+
+    pub fn handle_input(&mut self, player: u8, input: (u8, String)) {
+        if let Some(player) = self.players_mut().find(|p| p.index() == player) {
+            player.handle_input(input);
+        }
+    }
+
+    pub fn update<'d, SPI, DC>(
+        &mut self,
+        tiles: &impl SolidTiles,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let targets: Vec<Aabb> = self.players().map(|player| player.bounds()).collect();
+        // A failed draw must not leave the game logic half done, the first error is
+        // handed up once the frame is through.
+        let mut drawn = Ok(());
+
+        let shots: Vec<(Direction, Aabb)> = self
+            .players_mut()
+            .filter_map(|player| {
+                player
+                    .take_shot()
+                    .map(|direction| (direction, player.bounds()))
+            })
+            .collect();
+        for (direction, from) in shots {
+            self.fire(direction, from, tiles);
+        }
+
+        for index in 0..self.slots.len() {
+            let slot = &mut self.slots[index];
+            if !matches!(slot.state, SlotState::Active) {
+                continue;
+            }
+            let Some(entity) = slot.entity.as_mut() else {
+                continue;
+            };
+
+            if let Entity::Enemy(enemy) = &mut *entity
+                && let Some(target) = nearest(enemy.bounds(), &targets)
+            {
+                enemy.track(target);
+            }
+            drawn = drawn.and(entity.update_state(tiles, camera, display));
+
+            if !entity.is_alive() {
+                if let Entity::Player(player) = entity {
+                    self.events.push(GameEvent::PlayerDied {
+                        player: player.index(),
+                    });
+                }
+                slot.state = SlotState::Despawned;
+            }
+        }
+
+        self.resolve_projectile_hits();
+        self.resolve_contacts();
+        self.collect_pickups();
+        drawn
+    }
+
+    // Takes a projectile from the pool and places it just in front of `from`.
+    fn fire(&mut self, direction: Direction, from: Aabb, tiles: &impl SolidTiles) {
+        if self.len() >= self.capacity() {
+            return;
+        }
+        let Some(mut projectile) = self.projectile_pool.pop() else {
+            return;
+        };
+
+        let (cx, cy) = from.center();
+        let reach = (from.width.max(from.height) / 2 + PROJECTILE_SIZE / 2 + 1) as u8;
+        let (dx, dy) = direction.delta(reach);
+        let (x, y) = (cx + dx, cy + dy);
+
+        let blocked = x < 0
+            || y < 0
+            || collides_with_tiles(
+                &Aabb::from_center(x as u16, y as u16, PROJECTILE_SIZE, PROJECTILE_SIZE),
+                tiles,
+            );
+        if blocked {
+            self.projectile_pool.push_within_capacity(projectile).ok();
+            return;
+        }
+
+        projectile.reset(direction, x as u16, y as u16);
+        if self.spawn(Entity::Projectile(projectile)).is_ok() {
+            self.events.push(GameEvent::ProjectileFired);
+        }
+    }
+
+    fn resolve_projectile_hits(&mut self) {
+        for index in 0..self.slots.len() {
+            if !matches!(self.slots[index].state, SlotState::Active) {
+                continue;
+            }
+            let Some(Entity::Projectile(projectile)) = self.slots[index].entity.as_ref() else {
+                continue;
+            };
+            let (bounds, damage) = (projectile.bounds(), projectile.damage());
+
+            let Some(hit) = self.slots.iter().position(|slot| {
+                matches!(slot.state, SlotState::Active)
+                    && matches!(&slot.entity, Some(Entity::Enemy(enemy)) if enemy.bounds().intersects(&bounds))
+            }) else {
+                continue;
+            };
+
+            if let Some(Entity::Enemy(enemy)) = self.slots[hit].entity.as_mut() {
+                enemy.damage(damage);
+                if !enemy.is_alive() {
+                    self.slots[hit].state = SlotState::Despawned;
+                    self.events.push(GameEvent::EnemyKilled);
+                }
+            }
+            if let Some(Entity::Projectile(projectile)) = self.slots[index].entity.as_mut() {
+                projectile.expire();
+            }
+            self.slots[index].state = SlotState::Despawned;
+        }
+    }
+
+    // Enemies stop right next to the player instead of overlapping it, so touching
+    // counts as a hit.
+    fn resolve_contacts(&mut self) {
+        for index in 0..self.slots.len() {
+            if !matches!(self.slots[index].state, SlotState::Active) {
+                continue;
+            }
+            let Some(Entity::Player(player)) = self.slots[index].entity.as_ref() else {
+                continue;
+            };
+            let player_bounds = player.bounds();
+
+            let hit = self.iter().find_map(|(_, entity)| match entity {
+                Entity::Enemy(enemy)
+                    if collides_with_entity(&enemy.bounds().expand(1), &player_bounds) =>
+                {
+                    Some((enemy.contact_damage(), enemy.bounds()))
+                }
+                _ => None,
+            });
+
+            if let Some((damage, source)) = hit
+                && let Some(Entity::Player(player)) = self.slots[index].entity.as_mut()
+                && player.damage(damage, source)
+            {
+                self.events.push(GameEvent::PlayerDamaged {
+                    player: player.index(),
+                    hp: player.hp(),
+                });
+            }
+        }
+    }
+
+    // A pickup goes to whichever player touches it first.
+    fn collect_pickups(&mut self) {
+        let players: Vec<(u8, Aabb)> = self
+            .players()
+            .map(|player| (player.index(), player.bounds()))
+            .collect();
+        let mut heals: Vec<(u8, u8)> = Vec::new();
+
+        for slot in self.slots.iter_mut() {
+            if !matches!(slot.state, SlotState::Active) {
+                continue;
+            }
+            let Some(Entity::Pickup(pickup)) = slot.entity.as_ref() else {
+                continue;
+            };
+            let Some(&(player, _)) = players
+                .iter()
+                .find(|(_, bounds)| pickup.bounds().intersects(bounds))
+            else {
+                continue;
+            };
+
+            if let PickupKind::Heal(amount) = pickup.kind() {
+                heals.push((player, amount));
+            }
+            self.events.push(GameEvent::PickupCollected(pickup.kind()));
+            slot.state = SlotState::Despawned;
+        }
+
+        for (index, amount) in heals {
+            if let Some(player) = self.players_mut().find(|player| player.index() == index) {
+                player.heal(amount);
+            }
+        }
+    }
+
+    // Everything that happened since the last call, oldest first.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = GameEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    // Erases despawned entities, draws the ones spawned this frame and recycles slots.
+    pub fn flush<'d, SPI, DC>(
+        &mut self,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        // Like `update`, slots get recycled even when drawing fails.
+        let mut drawn = Ok(());
+        for index in 0..self.slots.len() {
+            let slot = &mut self.slots[index];
+            match slot.state {
+                SlotState::Despawned => {
+                    let entity = slot.entity.take();
+                    slot.state = SlotState::Free;
+                    slot.generation = slot.generation.wrapping_add(1);
+
+                    if let Some(entity) = entity {
+                        let bounds = entity.bounds();
+                        drawn = drawn.and(camera.fill(bounds, BACKGROUND_COLOR, display));
+                        // Whatever was underneath, e.g. the enemy a bullet just hit.
+                        drawn = drawn.and(self.redraw_overlapping(bounds, camera, display));
+
+                        if let Entity::Projectile(projectile) = entity {
+                            self.projectile_pool.push_within_capacity(projectile).ok();
+                        }
+                    }
+                }
+                SlotState::Spawned => {
+                    if let Some(entity) = slot.entity.as_mut() {
+                        drawn = drawn.and(entity.redraw(camera, display));
+                    }
+                    slot.state = SlotState::Active;
+                }
+                SlotState::Free | SlotState::Active => {}
+            }
+        }
+        drawn
+    }
+
+    // Draws every entity touching `area` again, e.g. after something got painted over it.
+    pub fn redraw_overlapping<'d, SPI, DC>(
+        &mut self,
+        area: Aabb,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        for (_, entity) in self.iter_mut() {
+            if entity.bounds().intersects(&area) {
+                entity.redraw(camera, display)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Full repaint, e.g. after something else covered the play field.
+    pub fn render<'d, SPI, DC>(
+        &mut self,
+        camera: &Camera,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        for (_, entity) in self.iter_mut() {
+            entity.redraw(camera, display)?;
+        }
+        Ok(())
+    }
+}
+
+// The target closest to `from`, by distance between centers.
+fn nearest(from: Aabb, targets: &[Aabb]) -> Option<Aabb> {
+    let (x, y) = from.center();
+    targets.iter().copied().min_by_key(|target| {
+        let (tx, ty) = target.center();
+        (tx - x).unsigned_abs() + (ty - y).unsigned_abs()
+    })
+}
@@ -0,0 +1,68 @@
+use alloc::{format, string::String};
+use esp_hal::{i2c, spi};
+
+use crate::{inputs::InputError, lcd::DisplayError, utils::AllocError};
+
+// Anything that stops the game, the module errors convert into it so code driving more
+// than one of them can hand errors up with `?`. None of these panic, the game shows them
+// on an error screen where it can and carries on once they are dealt with.
+#[derive(Debug)]
+pub enum Error {
+    Display(DisplayError),
+    Bus(BusError),
+    Input(InputError),
+    Alloc(AllocError),
+}
+
+// A bus that did not take its config. Transfer errors belong to whoever uses the bus.
+#[derive(Debug)]
+pub enum BusError {
+    Spi(spi::master::ConfigError),
+    I2c(i2c::master::ConfigError),
+}
+
+impl Error {
+    // Heading and text for the error screen.
+    pub fn describe(&self) -> (&'static str, String) {
+        match self {
+            Error::Display(e) => (
+                "DISPLAY ERROR",
+                format!("The panel stopped responding: {:?}", e),
+            ),
+            Error::Bus(e) => ("BUS ERROR", format!("A bus could not be set up: {:?}", e)),
+            Error::Input(InputError::NotFound) => (
+                "NO CONTROLLER",
+                String::from("No controller found, check the pad cable."),
+            ),
+            Error::Input(InputError::NoDevice { address, .. }) => (
+                "NO CONTROLLER",
+                format!("Nothing answers at 0x{:02X}, check the pad cable.", address),
+            ),
+            Error::Alloc(e) => ("OUT OF MEMORY", format!("{:?}", e)),
+        }
+    }
+}
+
+impl From<DisplayError> for Error {
+    fn from(e: DisplayError) -> Self {
+        Error::Display(e)
+    }
+}
+
+impl From<BusError> for Error {
+    fn from(e: BusError) -> Self {
+        Error::Bus(e)
+    }
+}
+
+impl From<InputError> for Error {
+    fn from(e: InputError) -> Self {
+        Error::Input(e)
+    }
+}
+
+impl From<AllocError> for Error {
+    fn from(e: AllocError) -> Self {
+        Error::Alloc(e)
+    }
+}
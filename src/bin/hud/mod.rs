@@ -0,0 +1,210 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+
+use crate::{
+    lcd::{Canvas, DisplayError, LcdDisplay, Screen, Scroll, ScrollArea},
+    text::fonts::FONT_6X10,
+    utils::AllocError,
+};
+
+// The band at the left edge of the screen, down its whole height. It is the fixed top
+// area of the hardware scroll, so the level scrolls past without touching it.
+pub const HUD_WIDTH: u16 = 64;
+
+const HUD_BACKGROUND: Rgb565 = Rgb565::BLACK;
+const HUD_TEXT: Rgb565 = Rgb565::WHITE;
+
+// Each value owns a row range of the band so it can be flushed on its own. FPS sits at
+// the bottom and is left out when it would run into the score. With more than one player
+// the health area is split between them.
+const HEALTH_AREA: (u16, u16) = (0, 64);
+const SCORE_AREA: (u16, u16) = (64, 92);
+const FPS_HEIGHT: u16 = 16;
+
+// From the top of a player's part of the health area, the label goes above it.
+const BAR_Y: i32 = 15;
+const BAR_MARGIN: u32 = 4;
+const BAR_HEIGHT: u32 = 8;
+
+pub struct Hud {
+    canvas: Canvas,
+    // Fixed rows do not move with the scroll offset, any offset flushes the same.
+    area: ScrollArea,
+    height: u16,
+    // One per player.
+    health: Vec<Option<(u8, u8)>>,
+    score: Option<u32>,
+    fps: Option<u32>,
+    dirty: Option<(u16, u16)>,
+}
+
+impl Hud {
+    // `area` has to keep at least `HUD_WIDTH` rows fixed at the top.
+    pub fn new(screen: Screen, area: ScrollArea) -> Result<Self, AllocError> {
+        let height = screen.height();
+        Ok(Hud {
+            canvas: Canvas::new(screen, 0, 0, HUD_WIDTH, height, HUD_BACKGROUND)?,
+            area,
+            height,
+            health: vec![None],
+            score: None,
+            fps: None,
+            dirty: Some((0, height)),
+        })
+    }
+
+    pub fn set_players(&mut self, players: u8) {
+        let players = players.max(1) as usize;
+        if self.health.len() == players {
+            return;
+        }
+        self.health = vec![None; players];
+        self.clear(HEALTH_AREA);
+        self.mark_dirty(HEALTH_AREA);
+    }
+
+    pub fn set_health(&mut self, player: u8, hp: u8, max_hp: u8) {
+        let players = self.health.len() as u16;
+        let Some(health) = self.health.get_mut(player as usize) else {
+            return;
+        };
+        if *health == Some((hp, max_hp)) {
+            return;
+        }
+        *health = Some((hp, max_hp));
+
+        let slot_height = (HEALTH_AREA.1 - HEALTH_AREA.0) / players;
+        let from = HEALTH_AREA.0 + slot_height * player as u16;
+        let area = (from, from + slot_height);
+        let y = from as i32;
+        let bar_width = HUD_WIDTH as u32 - 2 * BAR_MARGIN - 2;
+
+        self.clear(area);
+        let label = match players {
+            1 => String::from("HP"),
+            _ => format!("P{}", player + 1),
+        };
+        self.draw_text(&label, from);
+
+        let bar = Rectangle::new(
+            Point::new(BAR_MARGIN as i32, y + BAR_Y),
+            Size::new(bar_width + 2, BAR_HEIGHT),
+        );
+        bar.into_styled(PrimitiveStyle::with_stroke(HUD_TEXT, 1))
+            .draw(&mut self.canvas)
+            .ok();
+
+        let filled = match max_hp {
+            0 => 0,
+            max_hp => bar_width * hp.min(max_hp) as u32 / max_hp as u32,
+        };
+        let color = if hp as u32 * 2 > max_hp as u32 {
+            Rgb565::GREEN
+        } else if hp as u32 * 4 > max_hp as u32 {
+            Rgb565::CSS_ORANGE
+        } else {
+            Rgb565::RED
+        };
+        Rectangle::new(
+            Point::new(BAR_MARGIN as i32 + 1, y + BAR_Y + 1),
+            Size::new(filled, BAR_HEIGHT - 2),
+        )
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(&mut self.canvas)
+        .ok();
+
+        self.mark_dirty(area);
+    }
+
+    pub fn set_score(&mut self, score: u32) {
+        if self.score == Some(score) {
+            return;
+        }
+        self.score = Some(score);
+
+        let area = (SCORE_AREA.0, SCORE_AREA.1.min(self.height));
+        self.clear(area);
+        self.draw_text("SCORE", area.0);
+        self.draw_text(&format!("{:>8}", score), area.0 + 12);
+        self.mark_dirty(area);
+    }
+
+    pub fn set_fps(&mut self, fps: u32) {
+        if self.fps == Some(fps) {
+            return;
+        }
+        self.fps = Some(fps);
+        if self.height < SCORE_AREA.1 + FPS_HEIGHT {
+            return;
+        }
+
+        let area = (self.height - FPS_HEIGHT, self.height);
+        self.clear(area);
+        self.draw_text(&format!("FPS {:>4}", fps), area.0);
+        self.mark_dirty(area);
+    }
+
+    // Forces the whole band out on the next flush, e.g. after the screen got cleared.
+    pub fn invalidate(&mut self) {
+        self.mark_dirty((0, self.height));
+    }
+
+    // Sends only the rows that changed since the last flush.
+    pub fn flush<'d, SPI, DC>(
+        &mut self,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let Some((from, to)) = self.dirty.take() else {
+            return Ok(());
+        };
+
+        self.canvas
+            .flush_rows(from, to, Scroll::new(self.area, 0), display)
+    }
+
+    fn clear(&mut self, (from, to): (u16, u16)) {
+        Rectangle::new(
+            Point::new(0, from as i32),
+            Size::new(HUD_WIDTH as u32, (to - from) as u32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(HUD_BACKGROUND))
+        .draw(&mut self.canvas)
+        .ok();
+    }
+
+    fn draw_text(&mut self, text: &str, y: u16) {
+        Text::with_baseline(
+            text,
+            Point::new(BAR_MARGIN as i32, y as i32 + 3),
+            text_style(),
+            Baseline::Top,
+        )
+        .draw(&mut self.canvas)
+        .ok();
+    }
+
+    fn mark_dirty(&mut self, (from, to): (u16, u16)) {
+        self.dirty = Some(match self.dirty {
+            Some((dirty_from, dirty_to)) => (dirty_from.min(from), dirty_to.max(to)),
+            None => (from, to),
+        });
+    }
+}
+
+fn text_style() -> MonoTextStyle<'static, Rgb565> {
+    MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(HUD_TEXT)
+        .background_color(HUD_BACKGROUND)
+        .build()
+}
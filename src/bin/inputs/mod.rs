@@ -1,14 +1,27 @@
 use alloc::{string::String, vec::Vec};
+use core::ops::RangeInclusive;
 use esp_hal::{
     Blocking,
     gpio::{Input, InputConfig, interconnect::PeripheralOutput},
-    i2c::master::{I2c, Instance},
+    i2c::master::{Error as I2cError, I2c, Instance},
+    time::{Duration, Instant},
 };
 use log::{error, info};
 
-use crate::utils::vec_into_iram;
+use crate::error::BusError;
 
-static PCF8574_ADDRESS: u8 = 0x20;
+// The three address pins pick one of eight addresses, the A variant has a range of its own.
+pub const PCF8574_ADDRESSES: RangeInclusive<u8> = 0x20..=0x27;
+pub const PCF8574A_ADDRESSES: RangeInclusive<u8> = 0x38..=0x3F;
+// Everything a 7 bit address can be, without the reserved ones at either end.
+const I2C_ADDRESSES: RangeInclusive<u8> = 0x08..=0x77;
+
+// One pad per player.
+pub const MAX_PADS: usize = 2;
+// How often unplugged pads are looked for again.
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+// Errors after the first one are only counted for this long, then reported together.
+const ERROR_REPORT_INTERVAL: Duration = Duration::from_secs(5);
 
 pub const NUMPAD_UP: u8 = 0b1111_1011;
 pub const NUMPAD_DOWN: u8 = 0b1111_0111;
@@ -23,8 +36,68 @@ pub const NUMPAD_BUTTON_B: u8 = 0b0111_1111;
 
 pub const NUMPAD_IDLE: u8 = 0b1111_1111;
 
+// Which face button does what. Applied to the raw numpad byte before the game sees it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyMap {
+    Default,
+    Swapped,
+}
+
+impl KeyMap {
+    pub fn apply(&self, input: u8) -> u8 {
+        match (self, input) {
+            (KeyMap::Swapped, NUMPAD_BUTTON_A) => NUMPAD_BUTTON_B,
+            (KeyMap::Swapped, NUMPAD_BUTTON_B) => NUMPAD_BUTTON_A,
+            _ => input,
+        }
+    }
+
+    // Position in the settings menu, also what gets saved.
+    pub fn index(&self) -> u8 {
+        match self {
+            KeyMap::Default => 0,
+            KeyMap::Swapped => 1,
+        }
+    }
+
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            1 => KeyMap::Swapped,
+            _ => KeyMap::Default,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    // None of the addresses the expander can be at acknowledged.
+    NotFound,
+    // The expander stopped answering, e.g. the pad got unplugged.
+    NoDevice { address: u8, error: I2cError },
+}
+
+// Whether anything at all is held, e.g. to keep the screen from dimming.
+pub fn any_pressed(i2c_input: u8, ext_input: &str) -> bool {
+    (i2c_input != NUMPAD_IDLE && i2c_input != 0) || !ext_input.is_empty()
+}
+
+// An expander that answered at some point, its position in `I2cInputs::pads` is the
+// player it belongs to.
+struct Pad {
+    address: u8,
+    connected: bool,
+    // What was read last, already checked against the known buttons.
+    input: u8,
+}
+
 pub struct I2cInputs<'a> {
     i2c: I2c<'a, Blocking>,
+    // Where expanders are looked for, in order.
+    addresses: Vec<u8>,
+    pads: Vec<Pad>,
+    last_scan: Instant,
+    last_report: Option<Instant>,
+    suppressed: u32,
     left_bump: Option<Input<'a>>,
     right_bump: Option<Input<'a>>,
     menu: Option<Input<'a>>,
@@ -35,21 +108,155 @@ impl<'a> I2cInputs<'a> {
         i2c: impl Instance + 'd,
         sda: impl PeripheralOutput<'d>,
         scl: impl PeripheralOutput<'d>,
-    ) -> Self {
-        I2cInputs {
-            i2c: {
-                I2c::new(i2c, esp_hal::i2c::master::Config::default())
-                    .unwrap_or_else(|e| {
-                        error!("IC2 instance failed to create; ERROR: {}", e);
-                        panic!();
-                    })
-                    .with_sda(sda)
-                    .with_scl(scl)
-            },
+    ) -> Result<Self, BusError> {
+        let i2c = I2c::new(i2c, esp_hal::i2c::master::Config::default())
+            .map_err(BusError::I2c)?
+            .with_sda(sda)
+            .with_scl(scl);
+
+        Ok(I2cInputs {
+            i2c,
+            addresses: PCF8574_ADDRESSES.chain(PCF8574A_ADDRESSES).collect(),
+            pads: Vec::new(),
+            last_scan: Instant::now(),
+            last_report: None,
+            suppressed: 0,
             left_bump: None,
             right_bump: None,
             menu: None,
+        })
+    }
+
+    // Where to look for expanders, in order. Both PCF8574 and PCF8574A ranges by default.
+    // Expanders become players in the order they are found, so at startup the address
+    // decides who is player one.
+    pub fn with_addresses(mut self, addresses: impl IntoIterator<Item = u8>) -> Self {
+        self.addresses = addresses.into_iter().collect();
+        self
+    }
+
+    // Whether any pad answers.
+    pub fn is_connected(&self) -> bool {
+        self.pads.iter().any(|pad| pad.connected)
+    }
+
+    // How many pads were found so far, plugged in or not. Player indices go up to this.
+    pub fn pads(&self) -> u8 {
+        self.pads.len() as u8
+    }
+
+    // What `player`'s pad read last, 0 while it is unplugged.
+    pub fn pad_input(&self, player: u8) -> u8 {
+        match self.pads.get(player as usize) {
+            Some(pad) if pad.connected => pad.input,
+            _ => 0,
+        }
+    }
+
+    // Logs everything on the bus and picks the expanders out of it, for startup. Later on
+    // `read_inputs` finds pads that get plugged in by itself.
+    pub fn scan(&mut self) -> Result<u8, InputError> {
+        let mut buf = [0u8; 1];
+        let found: Vec<u8> = I2C_ADDRESSES
+            .filter(|&address| self.i2c.read(address, &mut buf).is_ok())
+            .collect();
+        info!("I2C devices at {:02X?}", found);
+
+        self.last_scan = Instant::now();
+        for address in self.addresses.clone() {
+            if found.contains(&address) {
+                self.add_pad(address);
+            }
+        }
+        match self.pads() {
+            0 => Err(InputError::NotFound),
+            pads => Ok(pads),
+        }
+    }
+
+    fn add_pad(&mut self, address: u8) {
+        if self.pads.len() >= MAX_PADS || self.pads.iter().any(|pad| pad.address == address) {
+            return;
+        }
+        info!(
+            "Input expander at 0x{:02X} is player {}",
+            address,
+            self.pads.len() + 1
+        );
+        self.pads.push(Pad {
+            address,
+            connected: true,
+            input: NUMPAD_IDLE,
+        });
+    }
+
+    // Every `RECONNECT_INTERVAL` unplugged pads are tried again at their own address and,
+    // while there is room, the other addresses for new ones.
+    fn rescan(&mut self, buf: &mut [u8]) {
+        if self.last_scan.elapsed() < RECONNECT_INTERVAL {
+            return;
+        }
+        self.last_scan = Instant::now();
+
+        for index in 0..self.pads.len() {
+            let address = self.pads[index].address;
+            if !self.pads[index].connected && self.i2c.read(address, buf).is_ok() {
+                info!("Player {} is back at 0x{:02X}", index + 1, address);
+                self.pads[index].connected = true;
+                self.last_report = None;
+            }
+        }
+
+        if self.pads.len() < MAX_PADS {
+            for address in self.addresses.clone() {
+                let known = self.pads.iter().any(|pad| pad.address == address);
+                if !known && self.i2c.read(address, buf).is_ok() {
+                    self.add_pad(address);
+                }
+            }
+        }
+
+        if self.pads.is_empty() {
+            self.report(InputError::NotFound);
+        }
+    }
+
+    fn read_pads(&mut self, buf: &mut [u8]) {
+        self.rescan(buf);
+
+        for index in 0..self.pads.len() {
+            let Pad {
+                address, connected, ..
+            } = self.pads[index];
+            if !connected {
+                continue;
+            }
+            match self.i2c.read(address, buf) {
+                Ok(_) => self.pads[index].input = decode(buf[0]),
+                Err(error) => {
+                    self.pads[index].connected = false;
+                    self.report(InputError::NoDevice { address, error });
+                }
+            }
+        }
+    }
+
+    // At most one line per `ERROR_REPORT_INTERVAL`, with a count of what it left out.
+    fn report(&mut self, e: InputError) {
+        if self
+            .last_report
+            .is_some_and(|at| at.elapsed() < ERROR_REPORT_INTERVAL)
+        {
+            self.suppressed += 1;
+            return;
+        }
+        if self.suppressed > 0 {
+            error!("{:?}, {} more since the last report", e, self.suppressed);
+        } else {
+            error!("{:?}", e);
         }
+        self.last_report = Some(Instant::now());
+        self.suppressed = 0;
     }
 
     pub fn with_ext_inputs<'d: 'a>(
@@ -64,52 +271,18 @@ impl<'a> I2cInputs<'a> {
         self
     }
 
+    // Reads every pad. What comes back is for menus: whichever pad has something pressed,
+    // player one's when none does. In game each player goes by `pad_input`, the buttons
+    // on the console count as player one's.
     pub fn read_inputs(&mut self, buf: &mut [u8]) -> (u8, String) {
-        let mut i2c_input: u8 = 0;
         let mut ext_input: String = String::new();
 
-        match self.i2c.read(PCF8574_ADDRESS, buf) {
-            Ok(_) => match buf[0] {
-                NUMPAD_UP => {
-                    //info!("NUMPAD_UP");
-                    i2c_input = NUMPAD_UP;
-                }
-                NUMPAD_DOWN => {
-                    //info!("NUMPAD_DOWN");
-                    i2c_input = NUMPAD_DOWN;
-                }
-                NUMPAD_LEFT => {
-                    //info!("NUMPAD_LEFT");
-                    i2c_input = NUMPAD_LEFT;
-                }
-                NUMPAD_RIGHT => {
-                    //info!("NUMPAD_RIGHT");
-                    i2c_input = NUMPAD_RIGHT;
-                }
-                NUMPAD_START => {
-                    //info!("START");
-                    i2c_input = NUMPAD_START;
-                }
-                NUMPAD_SELECT => {
-                    //info!("SELECT");
-                    i2c_input = NUMPAD_SELECT;
-                }
-                NUMPAD_BUTTON_A => {
-                    //info!("A");
-                    i2c_input = NUMPAD_BUTTON_A;
-                }
-                NUMPAD_BUTTON_B => {
-                    //info!("B");
-                    i2c_input = NUMPAD_BUTTON_B;
-                }
-                NUMPAD_IDLE => {
-                    //info!("IDLE");
-                    i2c_input = NUMPAD_IDLE;
-                }
-                _ => {}
-            },
-            Err(e) => error!("No device at {:X}, error: {:}", PCF8574_ADDRESS, e),
-        }
+        self.read_pads(buf);
+        let i2c_input = (0..self.pads())
+            .map(|player| self.pad_input(player))
+            .find(|&input| input != NUMPAD_IDLE && input != 0)
+            .unwrap_or_else(|| self.pad_input(0));
+
         if let Some(i) = self.left_bump.as_mut() {
             if i.is_low() {
                 //info!("LEFT_BUMP");
@@ -132,3 +305,12 @@ impl<'a> I2cInputs<'a> {
         (i2c_input, ext_input)
     }
 }
+
+// Known buttons pass through, anything else, like two held at once, reads as 0.
+fn decode(pins: u8) -> u8 {
+    match pins {
+        NUMPAD_UP | NUMPAD_DOWN | NUMPAD_LEFT | NUMPAD_RIGHT | NUMPAD_START | NUMPAD_SELECT
+        | NUMPAD_BUTTON_A | NUMPAD_BUTTON_B | NUMPAD_IDLE => pins,
+        _ => 0,
+    }
+}
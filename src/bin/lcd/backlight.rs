@@ -0,0 +1,111 @@
+use esp_hal::ledc::{
+    LowSpeed,
+    channel::{Channel, ChannelIFace},
+};
+use esp_hal::time::{Duration, Instant};
+use log::error;
+
+// Backlight driven by a LEDC PWM channel. Brightness is in percent.
+pub struct Backlight<'a> {
+    channel: Channel<'a, LowSpeed>,
+    brightness: u8,
+    // Duty the channel is at, or fading towards.
+    current: u8,
+    dim_after: Option<Duration>,
+    dim_level: u8,
+    last_activity: Instant,
+    dimmed: bool,
+}
+
+impl<'a> Backlight<'a> {
+    // Expects a channel that is already configured against its timer. Starts dark,
+    // `fade_in` turns it on.
+    pub fn new(channel: Channel<'a, LowSpeed>, brightness: u8) -> Self {
+        if channel.set_duty(0).is_err() {
+            error!("Could not set backlight duty");
+        }
+
+        Backlight {
+            channel,
+            brightness: brightness.min(100),
+            current: 0,
+            dim_after: None,
+            dim_level: 0,
+            last_activity: Instant::now(),
+            dimmed: false,
+        }
+    }
+
+    // Dims down to `dim_level` percent once `idle` passed without any input.
+    pub fn with_auto_dim(mut self, idle: Duration, dim_level: u8) -> Self {
+        self.dim_after = Some(idle);
+        self.dim_level = dim_level.min(100);
+        self
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    // A dark backlight stays dark, the next `fade_in` goes to the new brightness.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness.min(100);
+        if !self.dimmed && self.current != 0 {
+            self.set_duty(self.brightness);
+        }
+    }
+
+    pub fn fade_to(&mut self, brightness: u8, duration_ms: u16) {
+        let brightness = brightness.min(100);
+        if brightness == self.current {
+            return;
+        }
+
+        // The hardware fade refuses some combinations of range and duration, jump instead.
+        if self
+            .channel
+            .start_duty_fade(self.current, brightness, duration_ms)
+            .is_err()
+        {
+            self.set_duty(brightness);
+            return;
+        }
+        self.current = brightness;
+    }
+
+    pub fn fade_in(&mut self, duration_ms: u16) {
+        self.fade_to(self.brightness, duration_ms);
+    }
+
+    pub fn fade_out(&mut self, duration_ms: u16) {
+        self.fade_to(0, duration_ms);
+    }
+
+    // Call once per frame, `active` being whether any button is pressed.
+    pub fn update(&mut self, active: bool) {
+        if active {
+            self.last_activity = Instant::now();
+            if self.dimmed {
+                self.dimmed = false;
+                self.fade_in(200);
+            }
+            return;
+        }
+
+        if let Some(dim_after) = self.dim_after
+            && !self.dimmed
+            && self.last_activity.elapsed() >= dim_after
+        {
+            self.dimmed = true;
+            self.fade_to(self.dim_level.min(self.brightness), 1000);
+        }
+    }
+
+    fn set_duty(&mut self, duty: u8) {
+        if self.channel.set_duty(duty).is_err() {
+            error!("Could not set backlight duty");
+            return;
+        }
+        self.current = duty;
+    }
+}
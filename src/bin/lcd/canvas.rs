@@ -0,0 +1,162 @@
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+
+use crate::utils::{AllocError, Psram, Region};
+
+use super::{DisplayError, LcdDisplay, LcdMonitor, Screen, Scroll};
+
+// Off-screen buffer for anything drawn with embedded-graphics. The panel is driven in
+// portrait, so the canvas takes landscape coordinates (x to the right, y down, as the
+// player sees the screen) and stores pixels in panel order, which makes any column
+// range one contiguous `set_pixels`. It stays put on screen when the display is scrolled,
+// flushing takes the current scroll offset.
+pub struct Canvas {
+    x: u16,
+    // Where the bottom edge is along panel x.
+    panel_x: u16,
+    width: u16,
+    height: u16,
+    pixels: Vec<Rgb565, Psram>,
+}
+
+impl Canvas {
+    // `x`/`y` is the top left corner on screen, in landscape coordinates.
+    pub fn new(
+        screen: Screen,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Rgb565,
+    ) -> Result<Self, AllocError> {
+        let size = width as usize * height as usize;
+        let pixels = Psram.vec_from_elem(color, size)?;
+
+        Ok(Canvas {
+            x,
+            panel_x: screen.height().saturating_sub(y + height),
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn flush<'d, SPI, DC>(
+        &self,
+        scroll: Scroll,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        self.flush_columns(0, self.width, scroll, display)
+    }
+
+    // Sends the canvas columns `from..to` only.
+    pub fn flush_columns<'d, SPI, DC>(
+        &self,
+        from: u16,
+        to: u16,
+        scroll: Scroll,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let to = to.min(self.width);
+        if from >= to {
+            return Ok(());
+        }
+
+        let stride = self.height as usize;
+
+        LcdMonitor::set_pixels_scrolled(
+            display,
+            scroll,
+            self.panel_x,
+            self.x + from,
+            self.height,
+            to - from,
+            self.pixels[from as usize * stride..to as usize * stride]
+                .iter()
+                .copied(),
+        )
+    }
+
+    // Sends the canvas rows `from..to` only. Rows are not contiguous in panel order, so
+    // this picks them out of every column.
+    pub fn flush_rows<'d, SPI, DC>(
+        &self,
+        from: u16,
+        to: u16,
+        scroll: Scroll,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let to = to.min(self.height);
+        if from >= to {
+            return Ok(());
+        }
+
+        // Screen "down" runs towards panel x = 0, the last row comes first.
+        let stride = self.height as usize;
+        let rows = (self.height - to) as usize..(self.height - from) as usize;
+
+        LcdMonitor::set_pixels_scrolled(
+            display,
+            scroll,
+            self.panel_x + self.height - to,
+            self.x,
+            to - from,
+            self.width,
+            self.pixels
+                .chunks_exact(stride)
+                .flat_map(|column| column[rows.clone()].iter().copied()),
+        )
+    }
+}
+
+impl OriginDimensions for Canvas {
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl DrawTarget for Canvas {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x < 0
+                || point.y < 0
+                || point.x >= self.width as i32
+                || point.y >= self.height as i32
+            {
+                continue;
+            }
+            // Screen "down" runs towards panel x = 0.
+            let index = point.x as usize * self.height as usize + (self.height as usize - 1)
+                - point.y as usize;
+            self.pixels[index] = color;
+        }
+        Ok(())
+    }
+}
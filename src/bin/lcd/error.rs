@@ -0,0 +1,40 @@
+use esp_hal::ledc::{channel, timer};
+use mipidsi::interface::SpiError;
+
+// Why the panel or its backlight did not do what it was told. Bus errors only keep their
+// kind, so the generic SPI and DC pin types do not spread into everything that draws.
+#[derive(Debug)]
+pub enum DisplayError {
+    // The controller did not take its init sequence.
+    Init,
+    Spi(embedded_hal::spi::ErrorKind),
+    // The data/command pin could not be switched.
+    Pin(embedded_hal::digital::ErrorKind),
+    Backlight(channel::Error),
+    BacklightTimer(timer::Error),
+}
+
+impl<S, P> From<SpiError<S, P>> for DisplayError
+where
+    S: embedded_hal::spi::Error,
+    P: embedded_hal::digital::Error,
+{
+    fn from(e: SpiError<S, P>) -> Self {
+        match e {
+            SpiError::Spi(e) => DisplayError::Spi(e.kind()),
+            SpiError::Dc(e) => DisplayError::Pin(e.kind()),
+        }
+    }
+}
+
+impl From<channel::Error> for DisplayError {
+    fn from(e: channel::Error) -> Self {
+        DisplayError::Backlight(e)
+    }
+}
+
+impl From<timer::Error> for DisplayError {
+    fn from(e: timer::Error) -> Self {
+        DisplayError::BacklightTimer(e)
+    }
+}
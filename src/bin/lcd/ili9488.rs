@@ -0,0 +1,64 @@
+use embedded_graphics::pixelcolor::Rgb666;
+use embedded_hal::delay::DelayNs;
+use mipidsi::dcs::{
+    BitsPerPixel, EnterNormalMode, ExitSleepMode, InterfaceExt, PixelFormat, SetAddressMode,
+    SetDisplayOn, SetInvertMode, SetPixelFormat,
+};
+use mipidsi::interface::Interface;
+use mipidsi::models::Model;
+use mipidsi::options::ModelOptions;
+
+// Power control and VCOM values from the ILI9488 application notes.
+const CMD_POWER_CONTROL_1: u8 = 0xC0;
+const CMD_POWER_CONTROL_2: u8 = 0xC1;
+const CMD_VCOM_CONTROL: u8 = 0xC5;
+const CMD_INTERFACE_MODE: u8 = 0xB0;
+const CMD_FRAME_RATE: u8 = 0xB1;
+const CMD_DISPLAY_FUNCTION: u8 = 0xB6;
+
+// mipidsi has no ILI9488. Over SPI it only takes 18 bit pixels, so unlike the other
+// controllers every pixel gets widened to Rgb666 on its way out.
+pub struct ILI9488Rgb666;
+
+impl Model for ILI9488Rgb666 {
+    type ColorFormat = Rgb666;
+    const FRAMEBUFFER_SIZE: (u16, u16) = (320, 480);
+
+    fn init<DELAY, DI>(
+        &mut self,
+        di: &mut DI,
+        delay: &mut DELAY,
+        options: &ModelOptions,
+    ) -> Result<SetAddressMode, DI::Error>
+    where
+        DELAY: DelayNs,
+        DI: Interface,
+    {
+        let madctl = SetAddressMode::from(options);
+
+        delay.delay_us(120_000);
+        di.write_raw(CMD_POWER_CONTROL_1, &[0x17, 0x15])?;
+        di.write_raw(CMD_POWER_CONTROL_2, &[0x41])?;
+        di.write_raw(CMD_VCOM_CONTROL, &[0x00, 0x12, 0x80])?;
+        // SDO is not used, it would otherwise drive MISO.
+        di.write_raw(CMD_INTERFACE_MODE, &[0x80])?;
+        // 60 Hz.
+        di.write_raw(CMD_FRAME_RATE, &[0xA0])?;
+        di.write_raw(CMD_DISPLAY_FUNCTION, &[0x02, 0x02, 0x3B])?;
+
+        di.write_command(ExitSleepMode)?;
+        delay.delay_us(120_000);
+
+        let pf = PixelFormat::with_all(BitsPerPixel::from_rgb_color::<Self::ColorFormat>());
+        di.write_command(SetPixelFormat::new(pf))?;
+        di.write_command(madctl)?;
+        di.write_command(SetInvertMode::new(options.invert_colors))?;
+        di.write_command(EnterNormalMode)?;
+        di.write_command(SetDisplayOn)?;
+
+        // DISPON needs some time before the first pixels come in.
+        delay.delay_us(120_000);
+
+        Ok(madctl)
+    }
+}
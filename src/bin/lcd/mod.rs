@@ -1,24 +1,61 @@
 use alloc::vec::Vec;
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::WebColors;
 use embedded_hal::digital::OutputPin;
 use esp_hal::delay::Delay;
-use log::error;
-use mipidsi::interface::SpiInterface;
-use mipidsi::options::Orientation;
-use mipidsi::{Builder, models::ILI9341Rgb565};
+use esp_hal::gpio::{DriveMode, interconnect::PeripheralOutput};
+use esp_hal::ledc::{
+    Ledc, LowSpeed,
+    channel::{self, ChannelIFace},
+    timer::Timer,
+};
+use mipidsi::Builder;
+use mipidsi::dcs::{InterfaceExt, SetScrollArea};
+use mipidsi::interface::{Interface, SpiInterface};
+use mipidsi::options::TearingEffect;
 use mipidsi::{Display, NoResetPin};
 
-use crate::{MONITOR_COLLUMNS, MONITOR_HEIGHT, MONITOR_ROWS, MONITOR_WIDTH, vec_into_psram};
+mod backlight;
+mod canvas;
+mod error;
+#[cfg(feature = "ili9488")]
+mod ili9488;
+mod panel;
+mod screen;
+mod scroll;
+#[cfg(feature = "tearing-sync")]
+mod tearing;
+pub use backlight::Backlight;
+pub use canvas::Canvas;
+pub use error::DisplayError;
+use panel::to_panel;
+pub use panel::{DisplayConfig, PANEL, PanelModel};
+pub use screen::Screen;
+pub use scroll::{Scroll, ScrollArea};
+#[cfg(feature = "tearing-sync")]
+pub use tearing::TearingSync;
+
+pub const BACKGROUND_COLOR: Rgb565 = Rgb565::CSS_LIGHT_GREEN;
+
+pub type LcdDisplay<'d, SPI, DC> = Display<SpiInterface<'d, SPI, DC>, PanelModel, NoResetPin>;
+
+// DCS commands mipidsi has no wrapper for, all the supported controllers know them.
+const CMD_PARTIAL_MODE_ON: u8 = 0x12;
+const CMD_NORMAL_MODE_ON: u8 = 0x13;
+const CMD_PARTIAL_AREA: u8 = 0x30;
+const CMD_IDLE_MODE_OFF: u8 = 0x38;
+const CMD_IDLE_MODE_ON: u8 = 0x39;
 
 pub struct LcdMonitor;
 
 impl LcdMonitor {
     pub fn init_display_raw<'d, SPI, DC>(
         di: SpiInterface<'d, SPI, DC>,
+        config: &DisplayConfig,
         delay: &mut Delay,
         rst_pin: &mut impl OutputPin,
-    ) -> Option<Display<SpiInterface<'d, SPI, DC>, ILI9341Rgb565, NoResetPin>>
+    ) -> Result<LcdDisplay<'d, SPI, DC>, DisplayError>
     where
         SPI: embedded_hal::spi::SpiDevice,
         DC: embedded_hal::digital::OutputPin,
@@ -29,46 +66,204 @@ impl LcdMonitor {
         rst_pin.set_high().ok();
         delay.delay_millis(200u32);
 
-        if let Ok(b) = Builder::new(ILI9341Rgb565, di)
-            .orientation(Orientation::new().flip_horizontal())
-            .display_size(MONITOR_HEIGHT as u16, MONITOR_WIDTH as u16)
-            .color_order(mipidsi::options::ColorOrder::Bgr)
+        Builder::new(PanelModel, di)
+            .orientation(config.orientation)
+            .display_size(config.width, config.height)
+            .display_offset(config.offset.0, config.offset.1)
+            .color_order(config.color_order)
+            .invert_colors(config.invert_colors)
             .init(delay)
-        {
-            Some(b)
-        } else {
-            error!("Could not create Spi Display");
-            None
-        }
+            // mipidsi does not export its init error, there is little more to tell anyway.
+            .map_err(|_| DisplayError::Init)
+    }
+
+    pub fn init_backlight<'a>(
+        ledc: &Ledc<'a>,
+        timer: &'a Timer<'a, LowSpeed>,
+        pin: impl PeripheralOutput<'a>,
+        brightness: u8,
+    ) -> Result<Backlight<'a>, DisplayError> {
+        let mut channel = ledc.channel(channel::Number::Channel0, pin);
+
+        channel.configure(channel::config::Config {
+            timer,
+            duty_pct: 0,
+            drive_mode: DriveMode::PushPull,
+        })?;
+
+        Ok(Backlight::new(channel, brightness))
     }
 
     pub fn fill_monitor<'d, SPI, DC>(
-        display: &mut Display<SpiInterface<'d, SPI, DC>, ILI9341Rgb565, NoResetPin>,
+        display: &mut LcdDisplay<'d, SPI, DC>,
         color: Rgb565,
-    ) where
+    ) -> Result<(), DisplayError>
+    where
         SPI: embedded_hal::spi::SpiDevice,
         DC: embedded_hal::digital::OutputPin,
     {
-        let square_height = MONITOR_HEIGHT;
-        let square_width = MONITOR_WIDTH;
+        let screen = Screen::of(display);
+        let len = screen.width() as usize * screen.height() as usize;
+
+        display.set_pixels(
+            0,
+            0,
+            screen.height() - 1,
+            screen.width() - 1,
+            core::iter::repeat_n(to_panel(color), len),
+        )?;
+        Ok(())
+    }
 
-        let mut color_pixels = vec_into_psram::<Rgb565>(square_width * square_height).unwrap();
-        for _ in 0..(square_height * square_width) {
-            if let Err(color) = color_pixels.push_within_capacity(color) {
-                color_pixels.reserve_exact(1);
+    // Sleep in stops the panel and its oscillator, the frame memory is kept so `wake` brings
+    // back the same picture. Both take 120 ms, asking for the state the display is already
+    // in does nothing.
+    pub fn sleep<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        delay: &mut Delay,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        if display.is_sleeping() {
+            return Ok(());
+        }
+        Ok(display.sleep(delay)?)
+    }
 
-                color_pixels.push_within_capacity(color).unwrap();
-            };
+    pub fn wake<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        delay: &mut Delay,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        if !display.is_sleeping() {
+            return Ok(());
         }
+        Ok(display.wake(delay)?)
+    }
 
-        if let Err(_) = display.set_pixels(
-            0,
-            0,
-            square_height as u16,
-            square_width as u16,
-            color_pixels,
-        ) {
-            error!("Could not draw to monitor");
+    // Idle mode only shows 8 colors, the top bit of each channel, and draws less power.
+    pub fn set_idle<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        idle: bool,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let command = if idle {
+            CMD_IDLE_MODE_ON
+        } else {
+            CMD_IDLE_MODE_OFF
+        };
+        // SAFETY: idle mode only changes how the panel shows the frame memory, nothing
+        // mipidsi keeps track of.
+        Ok(unsafe { display.dcs() }.send_command(command, &[])?)
+    }
+
+    // Partial mode only drives the panel rows `first..=last` and leaves the others black,
+    // `None` goes back to normal mode. Rows run along screen x like the scroll area.
+    pub fn set_partial<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        rows: Option<(u16, u16)>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        // SAFETY: as with idle mode, the frame memory and the address window stay as
+        // mipidsi left them.
+        let dcs = unsafe { display.dcs() };
+        let Some((first, last)) = rows else {
+            return Ok(dcs.send_command(CMD_NORMAL_MODE_ON, &[])?);
+        };
+
+        let (first, last) = (first.to_be_bytes(), last.to_be_bytes());
+        dcs.send_command(CMD_PARTIAL_AREA, &[first[0], first[1], last[0], last[1]])?;
+        Ok(dcs.send_command(CMD_PARTIAL_MODE_ON, &[])?)
+    }
+
+    // Turns the TE output on for a `TearingSync` to wait on, it pulses once per frame.
+    pub fn set_tearing_effect<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        enabled: bool,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        Ok(display.set_tearing_effect(if enabled {
+            TearingEffect::Vertical
+        } else {
+            TearingEffect::Off
+        })?)
+    }
+
+    // VSCRDEF. Leaves the scroll offset alone, which has to be set again for the new area.
+    pub fn set_scroll_area<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        area: ScrollArea,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let (tfa, vsa, bfa) = area.definition();
+        // SAFETY: VSCRDEF only changes which rows the panel shows where, writes still go to
+        // the address window mipidsi sets up.
+        Ok(unsafe { display.dcs() }.write_command(SetScrollArea::new(tfa, vsa, bfa))?)
+    }
+
+    // VSCRSADD. `scroll` has to be in the area last given to `set_scroll_area`.
+    pub fn set_scroll_offset<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        scroll: Scroll,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let (tfa, _, _) = scroll.area().definition();
+        Ok(display.set_vertical_scroll_offset(tfa + scroll.offset())?)
+    }
+
+    // Like `set_pixels` with a size instead of an end, in screen rows while the display is
+    // scrolled by `scroll`. Rows in the scroll area get written where they show up, which
+    // can take a few `set_pixels`.
+    pub fn set_pixels_scrolled<'d, SPI, DC>(
+        display: &mut LcdDisplay<'d, SPI, DC>,
+        scroll: Scroll,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        pixels: impl IntoIterator<Item = Rgb565>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        if width == 0 {
+            return Ok(());
+        }
+
+        let mut pixels = pixels.into_iter();
+        for (row, count) in scroll.rows(y, height) {
+            display.set_pixels(
+                x,
+                row,
+                x + width - 1,
+                row + count - 1,
+                pixels
+                    .by_ref()
+                    .take(width as usize * count as usize)
+                    .map(to_panel),
+            )?;
         }
+        Ok(())
     }
 }
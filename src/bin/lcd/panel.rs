@@ -0,0 +1,109 @@
+use embedded_graphics::pixelcolor::Rgb565;
+use mipidsi::models::Model;
+use mipidsi::options::{ColorInversion, ColorOrder, Orientation};
+
+#[cfg(not(any(
+    feature = "ili9341",
+    feature = "st7789",
+    feature = "ili9342",
+    feature = "ili9488"
+)))]
+compile_error!("Pick a display controller feature: ili9341, st7789, ili9342 or ili9488");
+
+#[cfg(any(
+    all(
+        feature = "ili9341",
+        any(feature = "st7789", feature = "ili9342", feature = "ili9488")
+    ),
+    all(feature = "st7789", any(feature = "ili9342", feature = "ili9488")),
+    all(feature = "ili9342", feature = "ili9488"),
+))]
+compile_error!("Only one display controller feature can be enabled at a time");
+
+// How the panel is wired up and turned. The controller is picked with a cargo feature,
+// `PANEL` holds the matching config.
+//
+// Everything is drawn for a portrait frame memory: screen x runs along panel y, the gate
+// lines, and screen "down" towards panel x = 0. `orientation` has to end up there.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayConfig {
+    // In the panel's native orientation, what mipidsi takes as display size.
+    pub width: u16,
+    pub height: u16,
+    // Where the panel starts in the controller's frame memory, for panels smaller than it.
+    pub offset: (u16, u16),
+    pub color_order: ColorOrder,
+    pub invert_colors: ColorInversion,
+    pub orientation: Orientation,
+    // The hardware scrolls along the gate lines. On panels that are natively landscape
+    // those run along screen y, where the camera cannot use them.
+    pub hardware_scroll: bool,
+}
+
+#[cfg(feature = "ili9341")]
+pub use mipidsi::models::ILI9341Rgb565 as PanelModel;
+#[cfg(feature = "ili9341")]
+pub const PANEL: DisplayConfig = DisplayConfig {
+    width: 240,
+    height: 320,
+    offset: (0, 0),
+    color_order: ColorOrder::Bgr,
+    invert_colors: ColorInversion::Normal,
+    orientation: Orientation::new().flip_horizontal(),
+    hardware_scroll: true,
+};
+
+// The 2" boards, which have their colors inverted. The 1.3" ones are 240x240, with the
+// panel at the end of the frame memory: `height: 240` and `offset: (0, 80)`.
+#[cfg(feature = "st7789")]
+pub use mipidsi::models::ST7789 as PanelModel;
+#[cfg(feature = "st7789")]
+pub const PANEL: DisplayConfig = DisplayConfig {
+    width: 240,
+    height: 320,
+    offset: (0, 0),
+    color_order: ColorOrder::Rgb,
+    invert_colors: ColorInversion::Inverted,
+    orientation: Orientation::new().flip_horizontal(),
+    hardware_scroll: true,
+};
+
+// Natively landscape, turned on its side to match the others.
+#[cfg(feature = "ili9342")]
+pub use mipidsi::models::ILI9342CRgb565 as PanelModel;
+#[cfg(feature = "ili9342")]
+pub const PANEL: DisplayConfig = DisplayConfig {
+    width: 320,
+    height: 240,
+    offset: (0, 0),
+    color_order: ColorOrder::Bgr,
+    invert_colors: ColorInversion::Normal,
+    orientation: Orientation::new()
+        .rotate(mipidsi::options::Rotation::Deg90)
+        .flip_horizontal(),
+    hardware_scroll: false,
+};
+
+#[cfg(feature = "ili9488")]
+pub use super::ili9488::ILI9488Rgb666 as PanelModel;
+#[cfg(feature = "ili9488")]
+pub const PANEL: DisplayConfig = DisplayConfig {
+    width: 320,
+    height: 480,
+    offset: (0, 0),
+    color_order: ColorOrder::Bgr,
+    invert_colors: ColorInversion::Normal,
+    orientation: Orientation::new().flip_horizontal(),
+    hardware_scroll: true,
+};
+
+// What `set_pixels` takes. Everything is drawn in Rgb565 and converted on the way out.
+pub type PanelColor = <PanelModel as Model>::ColorFormat;
+
+#[allow(
+    clippy::useless_conversion,
+    reason = "PanelColor is Rgb565 itself on most controllers."
+)]
+pub fn to_panel(color: Rgb565) -> PanelColor {
+    color.into()
+}
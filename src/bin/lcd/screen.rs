@@ -0,0 +1,76 @@
+use embedded_graphics::prelude::OriginDimensions;
+use mipidsi::models::Model;
+use mipidsi::options::Rotation;
+
+use super::{LcdDisplay, PANEL, PanelModel};
+
+// The screen as the player sees it, in landscape: `width` runs along panel y and `height`
+// along panel x. Read from the display once it is set up, everything that lays itself out
+// on screen takes it instead of assuming a resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Screen {
+    width: u16,
+    height: u16,
+    // Rows in the controller's frame memory and the first one the panel shows. Scrolling
+    // is set up over all of them, which is more than `width` on panels smaller than the
+    // controller.
+    frame_rows: u16,
+    row_offset: u16,
+}
+
+impl Screen {
+    pub fn new(width: u16, height: u16) -> Self {
+        Screen {
+            width,
+            height,
+            frame_rows: width,
+            row_offset: 0,
+        }
+    }
+
+    pub fn with_frame(mut self, rows: u16, offset: u16) -> Self {
+        self.frame_rows = rows;
+        self.row_offset = offset;
+        self
+    }
+
+    pub fn of<'d, SPI, DC>(display: &LcdDisplay<'d, SPI, DC>) -> Self
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let size = display.size();
+        let frame_rows = <PanelModel as Model>::FRAMEBUFFER_SIZE.1;
+        // Turned by 180 or 270 degrees the rows are written from the end of the frame
+        // memory, mipidsi moves the panel offset along with them.
+        let row_offset = match display.orientation().rotation {
+            Rotation::Deg0 | Rotation::Deg90 => PANEL.offset.1,
+            Rotation::Deg180 | Rotation::Deg270 => frame_rows - PANEL.height - PANEL.offset.1,
+        };
+        Screen::new(size.height as u16, size.width as u16).with_frame(frame_rows, row_offset)
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn frame_rows(&self) -> u16 {
+        self.frame_rows
+    }
+
+    pub fn row_offset(&self) -> u16 {
+        self.row_offset
+    }
+
+    // Top left corner that centers a `width` by `height` box, or as close as it fits.
+    pub fn center(&self, width: u16, height: u16) -> (u16, u16) {
+        (
+            self.width.saturating_sub(width) / 2,
+            self.height.saturating_sub(height) / 2,
+        )
+    }
+}
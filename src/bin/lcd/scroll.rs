@@ -0,0 +1,108 @@
+use super::Screen;
+
+// Split of the panel rows for VSCRDEF: `top` rows at the start and `bottom` rows at the
+// end stay where they are, the ones between scroll. Rows run along screen x, so the
+// fixed areas are strips at the left and right edge of the screen, e.g. for a HUD that
+// does not move while the level scrolls past.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScrollArea {
+    top: u16,
+    bottom: u16,
+    // Panel rows the hardware scroll runs through, the screen width.
+    rows: u16,
+    // Frame memory rows and where the panel starts in them. VSCRDEF covers all of the
+    // frame memory, rows the panel does not show go to the fixed areas.
+    frame_rows: u16,
+    row_offset: u16,
+}
+
+impl ScrollArea {
+    // The whole panel scrolls, what the controller does after a reset.
+    pub fn full(screen: Screen) -> Self {
+        ScrollArea {
+            top: 0,
+            bottom: 0,
+            rows: screen.width(),
+            frame_rows: screen.frame_rows(),
+            row_offset: screen.row_offset(),
+        }
+    }
+
+    // `None` unless there is at least one row left to scroll.
+    pub fn new(screen: Screen, top: u16, bottom: u16) -> Option<Self> {
+        let area = ScrollArea {
+            top,
+            bottom,
+            ..ScrollArea::full(screen)
+        };
+        ((top as u32 + bottom as u32) < area.rows as u32).then_some(area)
+    }
+
+    pub fn top(&self) -> u16 {
+        self.top
+    }
+
+    pub fn bottom(&self) -> u16 {
+        self.bottom
+    }
+
+    // Rows that scroll.
+    pub fn lines(&self) -> u16 {
+        self.rows - self.top - self.bottom
+    }
+
+    // TFA, VSA and BFA for VSCRDEF, in frame memory rows. They add up to all of it.
+    pub fn definition(&self) -> (u16, u16, u16) {
+        let tfa = self.row_offset + self.top;
+        let vsa = self.lines();
+        (tfa, vsa, self.frame_rows.saturating_sub(tfa + vsa))
+    }
+}
+
+// How far the scroll area is scrolled: the row `offset` rows into it is shown first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scroll {
+    area: ScrollArea,
+    offset: u16,
+}
+
+impl Scroll {
+    pub fn new(area: ScrollArea, offset: u16) -> Self {
+        Scroll {
+            area,
+            offset: offset % area.lines(),
+        }
+    }
+
+    pub fn area(&self) -> ScrollArea {
+        self.area
+    }
+
+    pub fn offset(&self) -> u16 {
+        self.offset
+    }
+
+    // The panel rows that screen rows `y..y + height` are stored in, as runs of first
+    // row and count. Rows of the fixed areas map onto themselves, the scroll area wraps.
+    pub fn rows(self, y: u16, height: u16) -> impl Iterator<Item = (u16, u16)> {
+        let (top, lines) = (self.area.top, self.area.lines());
+        let end = y + height;
+        let mut y = y;
+
+        core::iter::from_fn(move || {
+            if y >= end {
+                return None;
+            }
+            let run = if y < top {
+                (y, end.min(top) - y)
+            } else if y < top + lines {
+                let line = (y - top + self.offset) % lines;
+                (top + line, (end.min(top + lines) - y).min(lines - line))
+            } else {
+                (y, end - y)
+            };
+            y += run.1;
+            Some(run)
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use esp_hal::gpio::Input;
+use esp_hal::time::{Duration, Instant};
+use log::warn;
+
+// A frame at the panel's default 70 Hz takes about 14 ms, give it some slack.
+const TE_TIMEOUT: Duration = Duration::from_millis(20);
+// Timeouts in a row until the pin is taken as not connected.
+const MAX_MISSES: u8 = 3;
+
+// Waits on the panel's TE (tearing effect) output, which goes high once the panel is
+// done scanning a frame. Drawing right after that stays ahead of the scan line for
+// a while, so sprites moving fast show up whole instead of torn in half.
+//
+// The panel only drives the pin after `LcdMonitor::set_tearing_effect`. If it never
+// toggles, e.g. because the wire is missing, waiting gets skipped from then on.
+pub struct TearingSync<'a> {
+    pin: Input<'a>,
+    misses: u8,
+}
+
+impl<'a> TearingSync<'a> {
+    pub fn new(pin: Input<'a>) -> Self {
+        TearingSync { pin, misses: 0 }
+    }
+
+    // Blocks until the next rising edge of TE.
+    pub fn wait(&mut self) {
+        if self.misses >= MAX_MISSES {
+            return;
+        }
+
+        let start = Instant::now();
+        let found = self.wait_for(false, start) && self.wait_for(true, start);
+        if found {
+            self.misses = 0;
+            return;
+        }
+
+        self.misses += 1;
+        if self.misses == MAX_MISSES {
+            warn!("No tearing effect signal, drawing without waiting for it");
+        }
+    }
+
+    fn wait_for(&self, high: bool, start: Instant) -> bool {
+        while self.pin.is_high() != high {
+            if start.elapsed() >= TE_TIMEOUT {
+                return false;
+            }
+        }
+        true
+    }
+}
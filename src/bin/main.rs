@@ -5,42 +5,114 @@
     reason = "mem::forget is generally not safe to do with esp_hal types, especially those \
     holding buffers for the duration of a data transfer."
 )]
-#![feature(vec_push_within_capacity)]
+#![feature(allocator_api)]
 #![feature(slice_as_array)]
 
+use core::ops::RangeInclusive;
 use embedded_graphics::pixelcolor::Rgb565;
 use embedded_graphics::prelude::{RgbColor, WebColors};
-use esp_alloc::HEAP;
+use embedded_hal::digital::Error as _;
 use esp_backtrace as _;
 use esp_hal::clock::CpuClock;
 use esp_hal::delay::Delay;
 use esp_hal::gpio::{Input, InputConfig, Level, Output, OutputConfig, Pull};
+use esp_hal::ledc::{
+    LSGlobalClkSource, Ledc, LowSpeed, channel,
+    timer::{self, TimerIFace},
+};
 use esp_hal::spi::master::{Config, Spi};
-use esp_hal::time::{Duration, Instant};
+use esp_hal::time::{Duration, Instant, Rate};
 use esp_hal::{Blocking, main, spi};
+use esp_storage::FlashStorage;
 use log::{error, info};
 
+mod error;
+use error::{BusError, Error};
 mod utils;
 use mipidsi::interface::SpiInterface;
-use utils::{buffer_into_iram, buffer_into_psram, vec_into_iram, vec_into_psram};
+#[cfg(feature = "memory-report")]
+use utils::memory::MemoryReport;
+use utils::{Iram, Region};
 mod inputs;
-use inputs::I2cInputs;
+use inputs::{
+    I2cInputs, NUMPAD_BUTTON_A, NUMPAD_IDLE, NUMPAD_START, PCF8574_ADDRESSES, PCF8574A_ADDRESSES,
+    any_pressed,
+};
 mod lcd;
-use lcd::LcdMonitor;
+#[cfg(feature = "tearing-sync")]
+use lcd::TearingSync;
+use lcd::{
+    BACKGROUND_COLOR, Backlight, DisplayError, LcdDisplay, LcdMonitor, PANEL, Screen, Scroll,
+    ScrollArea,
+};
 mod assets;
-use assets::Player;
-
-use crate::assets::Mob;
+use assets::{ColorEffect, Enemy, EnemyAi, Mob, Pickup, PickupKind, Player, Sprites};
+mod entities;
+use entities::{Entity, EntityManager, GameEvent};
+mod collision;
+use collision::{Aabb, TileGrid, collides_with_tiles};
+mod camera;
+use camera::{Camera, CameraMove};
+mod hud;
+use hud::{HUD_WIDTH, Hud};
+mod menu;
+use menu::{PauseEvent, PauseMenu};
+mod settings;
+use settings::Settings;
+mod text;
+use text::Dialog;
+mod audio;
+use audio::{Audio, Buzzer, Pattern, Sfx, THEME, Track, note};
+mod save;
+use save::{FlashPartition, SaveData, SaveSlots};
+mod pack;
+use pack::{AssetPack, PackError, Sound};
 
 extern crate alloc;
+use alloc::{boxed::Box, format, string::String, vec::Vec};
 
 const INTERNAL_HEAP_SIZE: usize = 98768;
 
-const MONITOR_WIDTH: usize = 320;
-const MONITOR_HEIGHT: usize = 240;
+const BACKLIGHT_DIM_AFTER: Duration = Duration::from_secs(30);
+const BACKLIGHT_DIM_LEVEL: u8 = 10;
+// Without input in the pause menu the display first only shows the menu, in 8 colors,
+// and then goes to sleep.
+const PAUSE_IDLE_AFTER: Duration = Duration::from_secs(45);
+const PAUSE_SLEEP_AFTER: Duration = Duration::from_secs(120);
+
+// Where the pads' expanders are looked for, in order. Pads become players in the order they
+// are found, so at startup the first address that answers is player one.
+const PAD_ADDRESSES: [RangeInclusive<u8>; 2] = [PCF8574_ADDRESSES, PCF8574A_ADDRESSES];
+
+const MAX_ENTITIES: u16 = 32;
+const MAX_PROJECTILES: u16 = 8;
+const ENEMY_SCORE: u32 = 100;
+
+const SAVE_PARTITION: &str = "save";
+const ASSET_PARTITION: &str = "assets";
+const LEVEL_MAP: &str = "level";
+// Size of the built-in level, one 320x240 screen tall and three wide.
+const LEVEL_ROWS: usize = 7;
+const LEVEL_COLLUMNS: usize = 30;
+// Middle of the first screen of the built-in level, further players start above.
+const PLAYER_START: (u16, u16) = (120, 160);
+const PLAYER_SPACING: u16 = 64;
+// The built-in sprite is red, the second player's turns blue. Sprites from the pack that
+// have no pure red in them only get darker.
+const PLAYER_TWO_SWAP: &[(Rgb565, Rgb565)] = &[(Rgb565::RED, Rgb565::BLUE)];
+const PLAYER_TWO_BRIGHTNESS: u8 = 75;
+const TILE_COLOR: Rgb565 = Rgb565::CSS_DARK_GREEN;
+// Largest the intro and error dialogs get, they shrink on smaller screens.
+const DIALOG_SIZE: (u16, u16) = (240, 150);
+const DIALOG_MARGIN: u16 = 8;
+// Shown instead of the error dialog when there is no memory left for it.
+const ERROR_COLOR: Rgb565 = Rgb565::CSS_DARK_RED;
 
-const MONITOR_COLLUMNS: usize = MONITOR_WIDTH / 32;
-const MONITOR_ROWS: usize = MONITOR_HEIGHT / 32;
+#[cfg(feature = "memory-report")]
+const MEMORY_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+const INTRO_TEXT: &str = "Move with the arrows and shoot with A. Enemies hurt on contact, \
+    pink hearts heal and yellow coins are worth points.\nSTART pauses the game.";
 
 // This creates a default app-descriptor required by the esp-idf bootloader.
 // For more information see: <https://docs.espressif.com/projects/esp-idf/en/stable/esp32/api-reference/system/app_image_format.html#application-description>
@@ -71,64 +143,897 @@ fn main() -> ! {
         InputConfig::default().with_pull(Pull::Up),
     );
 
-    let mut inputs = I2cInputs::new(peripherals.I2C0, peripherals.GPIO21, peripherals.GPIO22)
-        .with_ext_inputs(left_bump, right_bump, menu);
+    let inputs =
+        I2cInputs::new(peripherals.I2C0, peripherals.GPIO21, peripherals.GPIO22).map(|inputs| {
+            inputs
+                .with_addresses(PAD_ADDRESSES.into_iter().flatten())
+                .with_ext_inputs(left_bump, right_bump, menu)
+        });
 
     let dc = Output::new(peripherals.GPIO12, Level::High, OutputConfig::default());
     let cs = Output::new(peripherals.GPIO5, Level::High, OutputConfig::default());
-    let mut bl = Output::new(peripherals.GPIO27, Level::High, OutputConfig::default());
-    bl.set_high();
 
-    let spi_bus: Spi<'_, Blocking> = spi::master::Spi::new(peripherals.SPI2, Config::default())
-        .expect("Could not create spi bus")
-        .with_sck(peripherals.GPIO18)
-        .with_mosi(peripherals.GPIO23);
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+    let mut backlight_timer = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    let backlight_timer_config = backlight_timer.configure(timer::config::Config {
+        duty: timer::config::Duty::Duty10Bit,
+        clock_source: timer::LSClockSource::APBClk,
+        frequency: Rate::from_khz(5),
+    });
+
+    let buzzer = Buzzer::new(
+        ledc.timer::<LowSpeed>(timer::Number::Timer1),
+        channel::Number::Channel1,
+        peripherals.GPIO26.degrade(),
+    );
+    let mut audio = Audio::new(buzzer);
+
+    // Until the display is up there is nowhere to show errors but the log.
+    let spi_bus: Spi<'_, Blocking> =
+        match spi::master::Spi::new(peripherals.SPI2, Config::default()) {
+            Ok(spi) => spi
+                .with_sck(peripherals.GPIO18)
+                .with_mosi(peripherals.GPIO23),
+            Err(e) => halt(BusError::Spi(e).into()),
+        };
 
-    let spi_device = embedded_hal_bus::spi::ExclusiveDevice::new_no_delay(spi_bus, cs)
-        .expect("Could not create spi device");
+    let spi_device = match embedded_hal_bus::spi::ExclusiveDevice::new_no_delay(spi_bus, cs) {
+        Ok(device) => device,
+        Err(e) => halt(DisplayError::Pin(e.kind()).into()),
+    };
 
-    let buf = buffer_into_iram::<u8>(512).unwrap();
-    let buf = unsafe { &mut *buf };
+    let mut spi_buffer = match Iram.vec_from_elem(0u8, 512) {
+        Ok(buffer) => buffer.into_boxed_slice(),
+        Err(e) => halt(e.into()),
+    };
 
-    let spi_iface = SpiInterface::new(spi_device, dc, buf);
+    let spi_iface = SpiInterface::new(spi_device, dc, &mut spi_buffer);
 
     let mut rst = Output::new(peripherals.GPIO2, Level::Low, OutputConfig::default());
 
     let mut delay = Delay::new();
-    let mut monitor = LcdMonitor::init_display_raw(spi_iface, &mut delay, &mut rst).unwrap();
+    let mut monitor = match LcdMonitor::init_display_raw(spi_iface, &PANEL, &mut delay, &mut rst) {
+        Ok(monitor) => monitor,
+        Err(e) => halt(e.into()),
+    };
+    let screen = Screen::of(&monitor);
+    info!("Screen is {}x{}", screen.width(), screen.height());
+    // The HUD band stays put, the level scrolls past it.
+    let scroll_area = match ScrollArea::new(screen, HUD_WIDTH, 0) {
+        Some(area) => area,
+        None => ScrollArea::full(screen),
+    };
+    // Errors can come up before the settings are loaded, the saved brightness comes later.
+    let mut backlight = match backlight_timer_config
+        .map_err(DisplayError::from)
+        .and_then(|()| {
+            LcdMonitor::init_backlight(
+                &ledc,
+                &backlight_timer,
+                peripherals.GPIO27,
+                Settings::default().brightness,
+            )
+        }) {
+        Ok(backlight) => backlight.with_auto_dim(BACKLIGHT_DIM_AFTER, BACKLIGHT_DIM_LEVEL),
+        // Without it the screen stays dark, there is nothing to show the error on.
+        Err(e) => halt(e.into()),
+    };
 
-    LcdMonitor::fill_monitor(&mut monitor, Rgb565::CSS_LIGHT_GREEN);
+    let mut inputs = match inputs {
+        Ok(inputs) => inputs,
+        Err(e) => halt_on_screen(&mut monitor, &mut backlight, screen, e.into()),
+    };
+    // Until a pad answers, or the console buttons are used to play without one.
+    if let Err(e) = inputs.scan() {
+        show_error(
+            &mut monitor,
+            &mut backlight,
+            screen,
+            Scroll::new(scroll_area, 0),
+            &mut inputs,
+            &e.into(),
+        );
+    }
+
+    #[cfg(feature = "tearing-sync")]
+    let mut tearing = {
+        if let Err(e) = LcdMonitor::set_tearing_effect(&mut monitor, true) {
+            error!("Could not enable tearing effect output: {:?}", e);
+        }
+        TearingSync::new(Input::new(
+            peripherals.GPIO39,
+            InputConfig::default().with_pull(Pull::None),
+        ))
+    };
+
+    let mut flash = peripherals.FLASH;
+    let pack = open_pack(FlashStorage::new(flash.reborrow()));
+    // Shared by every mob for the whole run.
+    let sprites: &'static Sprites = match Sprites::load(pack.as_ref()) {
+        Ok(sprites) => Box::leak(Box::new(sprites)),
+        Err(e) => halt_on_screen(&mut monitor, &mut backlight, screen, e.into()),
+    };
+    let tiles = load_level(pack.as_ref());
+    let theme = load_theme(pack.as_ref());
+    // Only the index goes, sprites keep pointing into the mapped partition.
+    drop(pack);
 
-    let mut player_texture = vec_into_psram::<Rgb565>(32 * 32).unwrap();
+    if let Err(e) = LcdMonitor::set_scroll_area(&mut monitor, scroll_area) {
+        error!("Could not set up scrolling: {:?}", e);
+    }
+    let mut camera = Camera::new(screen, tiles.bounds(), scroll_area)
+        .with_hardware_scroll(PANEL.hardware_scroll);
 
-    for _ in 0..(32 * 32) {
-        if let Err(color) = player_texture.push_within_capacity(Rgb565::RED) {
-            player_texture.reserve_exact(1);
+    let mut entities = match EntityManager::with_capacity(MAX_ENTITIES)
+        .and_then(|entities| entities.with_projectile_pool(MAX_PROJECTILES, &sprites.projectile))
+    {
+        Ok(entities) => entities,
+        Err(e) => halt_on_screen(&mut monitor, &mut backlight, screen, e.into()),
+    };
+    // A player per pad, or one for the console buttons when there is none.
+    let mut players = inputs.pads().max(1);
+    spawn_level(&mut entities, sprites, players);
+    follow_player(&mut camera, &entities);
 
-            player_texture.push_within_capacity(color).unwrap();
+    let mut hud = match Hud::new(screen, scroll_area) {
+        Ok(hud) => hud,
+        Err(e) => halt_on_screen(&mut monitor, &mut backlight, screen, e.into()),
+    };
+    hud.set_players(players);
+    let mut saves = open_saves(FlashStorage::new(flash));
+    let mut save_data = SaveData::default();
+    Settings::default().store(&mut save_data);
+    if let Some(saves) = saves.as_mut() {
+        match saves.load(&mut save_data) {
+            Ok(()) => info!(
+                "Loaded save, high scores: {:?}",
+                save_data.high_scores.scores()
+            ),
+            Err(e) => info!("No save loaded: {:?}", e),
         }
     }
-    let mut player = Player::new(player_texture);
+    let mut settings = Settings::from_save(&save_data);
+    let mut pause_menu = match PauseMenu::new(screen, &settings) {
+        Ok(menu) => menu,
+        Err(e) => halt_on_screen(&mut monitor, &mut backlight, screen, e.into()),
+    };
+
+    // Draw errors are kept until the next frame comes around and then shown, holding the
+    // first one. Anything drawn after it in the same frame is redrawn once it is dealt with.
+    let mut drawn = draw_scene(&mut monitor, &camera, &tiles, &mut entities, &mut hud);
+
+    backlight.set_brightness(settings.brightness);
+    backlight.fade_in(500);
+
+    audio.set_volume(settings.volume);
+    audio.play_music(theme);
+    let mut last_frame = Instant::now();
 
-    player.draw(
-        (MONITOR_HEIGHT / 2) as u16,
-        (MONITOR_WIDTH / 2) as u16,
+    let mut buf = [0u8; 1];
+    // Buttons only count when pressed, a press that closes the intro does nothing in game.
+    let mut last_input: (u8, String) = (NUMPAD_IDLE, String::new());
+
+    let (intro_x, intro_y, intro_width, intro_height) = dialog_area(screen);
+    match Dialog::new(screen, intro_x, intro_y, intro_width, intro_height) {
+        Ok(mut intro) => {
+            intro.show("WELCOME", INTRO_TEXT);
+            loop {
+                let (i2c_input, ext_input) = inputs.read_inputs(&mut buf);
+                backlight.update(any_pressed(i2c_input, &ext_input));
+                audio.update(frame_ms(&mut last_frame));
+                // Without a pad only the console buttons are there, any of them counts as A.
+                let input = if ext_input.is_empty() {
+                    i2c_input
+                } else {
+                    NUMPAD_BUTTON_A
+                };
+                last_input = (i2c_input, ext_input);
+                if intro.handle_input(input) {
+                    break;
+                }
+                drawn = drawn.and(intro.draw(camera.scroll(), &mut monitor));
+                if let Err(e) = drawn {
+                    show_error(
+                        &mut monitor,
+                        &mut backlight,
+                        screen,
+                        camera.scroll(),
+                        &mut inputs,
+                        &Error::from(e),
+                    );
+                    drawn = draw_scene(&mut monitor, &camera, &tiles, &mut entities, &mut hud);
+                    intro.show("WELCOME", INTRO_TEXT);
+                }
+            }
+        }
+        // Only the intro is lost, the game itself does not need it.
+        Err(e) => error!("Could not show the intro: {:?}", e),
+    }
+    drawn = drawn.and(draw_scene(
         &mut monitor,
-    );
+        &camera,
+        &tiles,
+        &mut entities,
+        &mut hud,
+    ));
 
+    let mut score: u32 = 0;
+    let mut paused = false;
+    let mut pause_power = DisplayPower::On;
+    let mut last_pause_input = Instant::now();
     let mut running_fps: u32 = 0;
-    let mut buf = [0u8; 1];
+    #[cfg(feature = "memory-report")]
+    let mut memory_report = MemoryReport::new(MEMORY_REPORT_INTERVAL);
 
     loop {
         let delay_start = Instant::now();
         while delay_start.elapsed() < Duration::from_micros(1000_000) {
+            if let Err(e) = drawn {
+                show_error(
+                    &mut monitor,
+                    &mut backlight,
+                    screen,
+                    camera.scroll(),
+                    &mut inputs,
+                    &Error::from(e),
+                );
+                drawn = draw_scene(&mut monitor, &camera, &tiles, &mut entities, &mut hud);
+                if paused {
+                    pause_menu.open(NUMPAD_IDLE);
+                    drawn = drawn.and(pause_menu.draw(camera.scroll(), &mut monitor));
+                }
+                continue;
+            }
             running_fps = running_fps + 1;
+            #[cfg(feature = "memory-report")]
+            memory_report.update();
             let (i2c_input, ext_input) = inputs.read_inputs(&mut buf);
+            backlight.update(any_pressed(i2c_input, &ext_input));
+            audio.update(frame_ms(&mut last_frame));
+
+            // START and the menu button toggle the pause menu on press.
+            let pause_pressed = (i2c_input == NUMPAD_START && last_input.0 != NUMPAD_START)
+                || (ext_input == "BUMP" && last_input.1 != "BUMP");
+            last_input = (i2c_input, ext_input.clone());
+
+            if paused {
+                let active = any_pressed(i2c_input, &ext_input);
+                if active {
+                    last_pause_input = Instant::now();
+                }
+                let power = if active {
+                    DisplayPower::On
+                } else if last_pause_input.elapsed() >= PAUSE_SLEEP_AFTER {
+                    DisplayPower::Asleep
+                } else if last_pause_input.elapsed() >= PAUSE_IDLE_AFTER {
+                    DisplayPower::Idle
+                } else {
+                    pause_power
+                };
+                if power != pause_power {
+                    let waking = pause_power != DisplayPower::On;
+                    set_display_power(
+                        &mut monitor,
+                        &mut backlight,
+                        &mut delay,
+                        power,
+                        pause_menu.rows(),
+                    );
+                    pause_power = power;
+                    // The press that wakes the display does nothing else.
+                    if waking {
+                        continue;
+                    }
+                }
+
+                let event = if pause_pressed {
+                    Some(PauseEvent::Resume)
+                } else {
+                    pause_menu.handle_input(i2c_input, &mut settings)
+                };
+
+                match event {
+                    Some(PauseEvent::Resume) => {
+                        paused = false;
+                        settings.store(&mut save_data);
+                        write_save(&mut saves, &save_data);
+                        audio.play_music(theme);
+                        drawn = drawn.and(draw_scene(
+                            &mut monitor,
+                            &camera,
+                            &tiles,
+                            &mut entities,
+                            &mut hud,
+                        ));
+                    }
+                    Some(PauseEvent::Restart) => {
+                        paused = false;
+                        settings.store(&mut save_data);
+                        write_save(&mut saves, &save_data);
+                        audio.play_music(theme);
+                        score = 0;
+                        drawn = drawn.and(reset_run(
+                            &mut monitor,
+                            &mut camera,
+                            &tiles,
+                            &mut entities,
+                            &mut hud,
+                            sprites,
+                            players,
+                        ));
+                    }
+                    None => drawn = drawn.and(pause_menu.draw(camera.scroll(), &mut monitor)),
+                }
+                if settings.brightness != backlight.brightness() {
+                    backlight.set_brightness(settings.brightness);
+                }
+                if settings.volume != audio.volume() {
+                    audio.set_volume(settings.volume);
+                }
+                continue;
+            }
+
+            if pause_pressed {
+                paused = true;
+                last_pause_input = Instant::now();
+                audio.stop_music();
+                pause_menu.open(i2c_input);
+                drawn = drawn.and(pause_menu.draw(camera.scroll(), &mut monitor));
+                continue;
+            }
+
+            // Pads plugged in mid game join right away, next to whoever is playing.
+            while players < inputs.pads() {
+                let mut player = new_player(sprites, players);
+                if let Some((x, y)) = join_position(&entities, &tiles) {
+                    player = player.with_position(x, y);
+                }
+                if let Err(e) = entities.spawn(Entity::Player(player)) {
+                    error!("Could not spawn player {}: {:?}", players + 1, e);
+                }
+                players += 1;
+                hud.set_players(players);
+            }
+            for player in 0..players {
+                let input = settings.key_map.apply(inputs.pad_input(player));
+                let ext_input = match player {
+                    0 => ext_input.clone(),
+                    _ => String::new(),
+                };
+                entities.handle_input(player, (input, ext_input));
+            }
+            #[cfg(feature = "tearing-sync")]
+            tearing.wait();
+            drawn = drawn.and(entities.update(&tiles, &camera, &mut monitor));
+
+            for event in entities.drain_events() {
+                match event {
+                    GameEvent::PickupCollected(PickupKind::Score(points)) => {
+                        score += points;
+                        audio.play_effect(Sfx::Coin);
+                    }
+                    GameEvent::PickupCollected(PickupKind::Heal(_)) => audio.play_effect(Sfx::Heal),
+                    GameEvent::PlayerDamaged { player, hp } => {
+                        info!("Player {} hit, {} hp left", player + 1, hp);
+                        audio.play_effect(Sfx::Hurt);
+                    }
+                    GameEvent::PlayerDied { player } => {
+                        info!("Player {} is down", player + 1);
+                        // No longer in the pool to report its health, leave an empty bar.
+                        hud.set_health(player, 0, 0);
+                    }
+                    GameEvent::EnemyKilled => {
+                        score += ENEMY_SCORE;
+                        audio.play_effect(Sfx::EnemyKilled);
+                    }
+                    GameEvent::ProjectileFired => audio.play_effect(Sfx::Shoot),
+                }
+            }
+            drawn = drawn.and(entities.flush(&camera, &mut monitor));
+
+            // A player that goes down stays down, the run only ends once nobody is left.
+            // The score is shared, so it gets banked once for everyone.
+            if entities.players().next().is_none() {
+                info!("Game over, final score: {}", score);
+                if let Some(rank) = save_data.high_scores.insert(score) {
+                    info!("New high score, rank {}", rank + 1);
+                    write_save(&mut saves, &save_data);
+                }
+                score = 0;
+                audio.play_effect(Sfx::GameOver);
+                drawn = drawn.and(reset_run(
+                    &mut monitor,
+                    &mut camera,
+                    &tiles,
+                    &mut entities,
+                    &mut hud,
+                    sprites,
+                    players,
+                ));
+            }
+
+            if let Some(players) = players_target(&camera, &entities) {
+                match camera.follow(players) {
+                    CameraMove::Scrolled(strip) => {
+                        drawn = drawn.and(draw_strip(
+                            &mut monitor,
+                            &camera,
+                            &tiles,
+                            &mut entities,
+                            strip,
+                        ));
+                    }
+                    CameraMove::Jumped => {
+                        drawn = drawn.and(draw_scene(
+                            &mut monitor,
+                            &camera,
+                            &tiles,
+                            &mut entities,
+                            &mut hud,
+                        ));
+                    }
+                    CameraMove::None => {}
+                }
+            }
 
-            player.update_state((i2c_input, ext_input), &mut monitor);
+            for player in entities.players() {
+                hud.set_health(player.index(), player.hp(), player.max_hp());
+            }
+            hud.set_score(score);
+            drawn = drawn.and(hud.flush(&mut monitor));
         }
-        //info!("HEAP STATS: {}", HEAP.stats());
-        info!("FPS: {}", running_fps);
+        info!("FPS: {}, SCORE: {}", running_fps, score);
+        hud.set_fps(running_fps);
         running_fps = 0;
     }
 }
+
+fn open_saves(flash: FlashStorage<'_>) -> Option<SaveSlots<FlashPartition<'_>>> {
+    let partition = match FlashPartition::find(flash, SAVE_PARTITION) {
+        Ok(partition) => partition,
+        Err(e) => {
+            error!("Could not open save partition: {:?}", e);
+            return None;
+        }
+    };
+    match SaveSlots::new(partition) {
+        Ok(saves) => Some(saves),
+        Err(e) => {
+            error!("Save partition unusable: {:?}", e);
+            None
+        }
+    }
+}
+
+// The partition gets mapped into the address space for the rest of the run, so loaded
+// sprites keep reading their pixels from flash.
+fn open_pack(flash: FlashStorage<'_>) -> Option<AssetPack<'static>> {
+    let partition = match FlashPartition::find(flash, ASSET_PARTITION) {
+        Ok(partition) => partition,
+        Err(e) => {
+            error!("Could not open asset partition: {:?}", e);
+            return None;
+        }
+    };
+    let bytes = match pack::map_partition(&partition) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Could not map asset partition: {:?}", e);
+            return None;
+        }
+    };
+    match AssetPack::open(bytes) {
+        Ok(pack) => {
+            info!("Asset pack with {} entries", pack.entries().len());
+            Some(pack)
+        }
+        Err(e) => {
+            error!("No usable asset pack, using built-in assets: {:?}", e);
+            None
+        }
+    }
+}
+
+// The level from the pack when it has one, else the built-in one.
+fn load_level(pack: Option<&AssetPack<'_>>) -> TileGrid {
+    match pack.map(|pack| pack.map(LEVEL_MAP)) {
+        Some(Ok(map)) if map.width > 0 && map.height > 0 => {
+            let mut tiles = TileGrid::new(map.height as usize, map.width as usize);
+            // Map rows run down the screen, tile rows count up from the bottom.
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    let tx = (map.height - 1 - y) as usize;
+                    tiles.set_solid(tx, x as usize, map.tile(x, y) != 0);
+                }
+            }
+            return tiles;
+        }
+        Some(Ok(_)) => error!("Level map is empty"),
+        Some(Err(PackError::NotFound)) => info!("No level map in the asset pack"),
+        Some(Err(e)) => error!("Could not load level map: {:?}", e),
+        None => {}
+    }
+
+    let mut tiles = TileGrid::new(LEVEL_ROWS, LEVEL_COLLUMNS);
+    for ty in [1, 8, 12, 18, 22, 27] {
+        tiles.set_solid(1, ty, true);
+        tiles.set_solid(5, ty, true);
+    }
+    for ty in [3, 6, 14, 15, 24] {
+        tiles.set_solid(3, ty, true);
+    }
+    tiles
+}
+
+// The theme from the pack, else the built-in one. Loaded patterns are needed for the
+// whole run and the audio engine wants them `'static`, so they are leaked, but only once
+// both loaded.
+fn load_theme(pack: Option<&AssetPack<'_>>) -> &'static Track {
+    let Some(pack) = pack else {
+        return &THEME;
+    };
+    let sound = |name: &str| match pack.sound(name) {
+        Ok(sound) => Some(sound),
+        Err(e) => {
+            error!("Could not load {}: {:?}", name, e);
+            None
+        }
+    };
+    let (Some(lead), Some(bass)) = (sound("theme_lead"), sound("theme_bass")) else {
+        return &THEME;
+    };
+
+    let pattern = |sound: Sound| -> &'static Pattern {
+        let steps: Vec<_> = sound
+            .steps
+            .iter()
+            .map(|&(frequency, rows)| note(frequency, rows))
+            .collect();
+        Box::leak(Box::new(Pattern {
+            steps: steps.leak(),
+            row_ms: sound.row_ms,
+            volume: sound.volume,
+        }))
+    };
+    Box::leak(Box::new([pattern(lead), pattern(bass)]))
+}
+
+// Centered on screen, as large as `DIALOG_SIZE` allows: x, y, width and height.
+fn dialog_area(screen: Screen) -> (u16, u16, u16, u16) {
+    let width = DIALOG_SIZE.0.min(screen.width() - 2 * DIALOG_MARGIN);
+    let height = DIALOG_SIZE.1.min(screen.height() - 2 * DIALOG_MARGIN);
+    let (x, y) = screen.center(width, height);
+    (x, y, width, height)
+}
+
+// Puts `error` on screen, in a dialog when there is memory for one.
+fn error_dialog<'d, SPI, DC>(
+    monitor: &mut LcdDisplay<'d, SPI, DC>,
+    screen: Screen,
+    error: &Error,
+    footer: &str,
+) -> Option<Dialog>
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    let (title, text) = error.describe();
+    let (x, y, width, height) = dialog_area(screen);
+    match Dialog::new(screen, x, y, width, height) {
+        Ok(mut dialog) => {
+            dialog.show(title, &format!("{}\n\n{}", text, footer));
+            Some(dialog)
+        }
+        Err(e) => {
+            error!("No memory for the error dialog: {:?}", e);
+            if let Err(e) = LcdMonitor::fill_monitor(monitor, ERROR_COLOR) {
+                error!("Could not show error: {:?}", e);
+            }
+            None
+        }
+    }
+}
+
+// Shows `error` until a button is pressed and released, or for a missing pad until it
+// answers again. The display may be what failed, so its own errors are only logged here,
+// the caller redraws whatever was on screen afterwards.
+fn show_error<'d, SPI, DC>(
+    monitor: &mut LcdDisplay<'d, SPI, DC>,
+    backlight: &mut Backlight,
+    screen: Screen,
+    scroll: Scroll,
+    inputs: &mut I2cInputs,
+    error: &Error,
+) where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    error!("{:?}", error);
+    let mut dialog = error_dialog(monitor, screen, error, "Press any button to try again.");
+    backlight.fade_in(200);
+    let missing_pad = matches!(error, Error::Input(_));
+
+    let mut buf = [0u8; 1];
+    let mut pressed = false;
+    loop {
+        if let Some(d) = dialog.as_mut() {
+            if let Err(e) = d.draw(scroll, monitor) {
+                error!("Could not draw error dialog: {:?}", e);
+                dialog = None;
+            }
+        }
+
+        let (i2c_input, ext_input) = inputs.read_inputs(&mut buf);
+        if missing_pad && inputs.is_connected() {
+            return;
+        }
+        // The press that dismisses it is released before the game sees any input again.
+        if any_pressed(i2c_input, &ext_input) {
+            pressed = true;
+        } else if pressed {
+            return;
+        }
+    }
+}
+
+// For errors the game cannot get past before the display is up.
+fn halt(error: Error) -> ! {
+    error!("Cannot continue: {:?}", error);
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+// For errors the game cannot get past, with the display there to show them.
+fn halt_on_screen<'d, SPI, DC>(
+    monitor: &mut LcdDisplay<'d, SPI, DC>,
+    backlight: &mut Backlight,
+    screen: Screen,
+    error: Error,
+) -> !
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    let mut dialog = error_dialog(monitor, screen, &error, "Please restart the console.");
+    backlight.fade_in(200);
+    if let Some(dialog) = dialog.as_mut() {
+        let scroll = Scroll::new(ScrollArea::full(screen), 0);
+        // Typed out like any other dialog, then it stays.
+        while dialog.draw(scroll, monitor).is_ok() && !dialog.is_done() {}
+    }
+    halt(error)
+}
+
+// Nothing gets written when the data did not change since the last save.
+fn write_save(saves: &mut Option<SaveSlots<FlashPartition<'_>>>, data: &SaveData) {
+    let Some(saves) = saves else {
+        return;
+    };
+    if let Err(e) = saves.save(data) {
+        error!("Could not write save: {:?}", e);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DisplayPower {
+    On,
+    // Idle and partial mode, only `rows` of the screen stay lit.
+    Idle,
+    Asleep,
+}
+
+// Moves the display to `power`, from whichever state it is in. `rows` is what stays
+// visible in idle, in screen x.
+fn set_display_power<'d, SPI, DC>(
+    monitor: &mut LcdDisplay<'d, SPI, DC>,
+    backlight: &mut Backlight,
+    delay: &mut Delay,
+    power: DisplayPower,
+    rows: (u16, u16),
+) where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    let (idle, partial, asleep) = match power {
+        DisplayPower::On => (false, None, false),
+        DisplayPower::Idle => (true, Some(rows), false),
+        DisplayPower::Asleep => (true, Some(rows), true),
+    };
+
+    if !asleep && LcdMonitor::wake(monitor, delay).is_err() {
+        error!("Could not wake display");
+    }
+    if LcdMonitor::set_partial(monitor, partial).is_err()
+        || LcdMonitor::set_idle(monitor, idle).is_err()
+    {
+        error!("Could not set display mode");
+    }
+
+    if asleep {
+        backlight.fade_out(500);
+        if LcdMonitor::sleep(monitor, delay).is_err() {
+            error!("Could not put display to sleep");
+        }
+    } else if power == DisplayPower::On {
+        backlight.fade_in(200);
+    }
+}
+
+// Milliseconds since the previous call, for the audio clock.
+fn frame_ms(last_frame: &mut Instant) -> u32 {
+    let elapsed = last_frame.elapsed().as_millis() as u32;
+    // Only whole milliseconds are taken, frames are often shorter than that.
+    *last_frame += Duration::from_millis(elapsed as u64);
+    elapsed
+}
+
+fn new_player(sprites: &'static Sprites, index: u8) -> Player {
+    let player = Player::new(&sprites.player)
+        .with_index(index)
+        .with_position(
+            PLAYER_START.0 + index as u16 * PLAYER_SPACING,
+            PLAYER_START.1,
+        );
+    match index {
+        0 => player,
+        _ => player.with_effect(
+            ColorEffect::NONE
+                .with_swap(PLAYER_TWO_SWAP)
+                .with_brightness(PLAYER_TWO_BRIGHTNESS),
+        ),
+    }
+}
+
+fn spawn_level(entities: &mut EntityManager, sprites: &'static Sprites, players: u8) {
+    let patrol = Enemy::new(
+        &sprites.enemy_patrol,
+        EnemyAi::Patrol {
+            from: (48, 112),
+            to: (48, 208),
+        },
+    )
+    .with_position(48, 112);
+    let guard =
+        Enemy::new(&sprites.enemy_guard, EnemyAi::Guard { sight: 96 }).with_position(208, 48);
+    let heal = Pickup::new(&sprites.heal, PickupKind::Heal(20)).with_position(208, 272);
+    let coin = Pickup::new(&sprites.coin, PickupKind::Score(50)).with_position(112, 24);
+
+    // Further along the level, off screen at the start.
+    let far_patrol = Enemy::new(
+        &sprites.enemy_patrol,
+        EnemyAi::Patrol {
+            from: (208, 352),
+            to: (208, 608),
+        },
+    )
+    .with_position(208, 352);
+    let far_guard =
+        Enemy::new(&sprites.enemy_guard, EnemyAi::Guard { sight: 96 }).with_position(112, 656);
+    let far_heal = Pickup::new(&sprites.heal, PickupKind::Heal(20)).with_position(176, 800);
+    let far_coins = [(48, 464), (112, 912)]
+        .map(|(x, y)| Pickup::new(&sprites.coin, PickupKind::Score(50)).with_position(x, y));
+
+    for entity in (0..players)
+        .map(|player| Entity::Player(new_player(sprites, player)))
+        .chain([
+            Entity::Enemy(patrol),
+            Entity::Enemy(guard),
+            Entity::Pickup(heal),
+            Entity::Pickup(coin),
+            Entity::Enemy(far_patrol),
+            Entity::Enemy(far_guard),
+            Entity::Pickup(far_heal),
+        ])
+        .chain(far_coins.map(Entity::Pickup))
+    {
+        if let Err(e) = entities.spawn(entity) {
+            error!("Could not spawn entity: {:?}", e);
+        }
+    }
+}
+
+// Starts the run over: every slot of the entity pool is freed, the level respawns with
+// all players at full health and the camera snaps back to them before a full repaint.
+fn reset_run<'d, SPI, DC>(
+    monitor: &mut LcdDisplay<'d, SPI, DC>,
+    camera: &mut Camera,
+    tiles: &TileGrid,
+    entities: &mut EntityManager,
+    hud: &mut Hud,
+    sprites: &'static Sprites,
+    players: u8,
+) -> Result<(), DisplayError>
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    entities.reset();
+    spawn_level(entities, sprites, players);
+    follow_player(camera, entities);
+    for player in entities.players() {
+        hud.set_health(player.index(), player.hp(), player.max_hp());
+    }
+    draw_scene(monitor, camera, tiles, entities, hud)
+}
+
+// Snaps the camera onto the players, for when the level (re)starts. Needs a full repaint.
+fn follow_player(camera: &mut Camera, entities: &EntityManager) {
+    if let Some(players) = players_target(camera, entities) {
+        camera.follow(players);
+    }
+}
+
+// Every player that is still up, or as many as fit the view around the first of them.
+fn players_target(camera: &Camera, entities: &EntityManager) -> Option<Aabb> {
+    let lead = entities.players().next()?.bounds();
+    Some(camera.fit(entities.players_bounds()?, lead))
+}
+
+// Next to a player that is still up, for a pad plugged in mid game. The first spot that is
+// in the level and clear of walls, on top of that player when there is none.
+fn join_position(entities: &EntityManager, tiles: &TileGrid) -> Option<(u16, u16)> {
+    let lead = entities.players().next()?.bounds();
+    let world = tiles.bounds();
+    let spacing = PLAYER_SPACING as i32;
+
+    let spot = [(spacing, 0), (-spacing, 0), (0, spacing), (0, -spacing)]
+        .into_iter()
+        .map(|(dx, dy)| lead.translate(dx, dy))
+        .find(|spot| spot.intersection(&world) == Some(*spot) && !collides_with_tiles(spot, tiles))
+        .unwrap_or(lead);
+    let (x, y) = spot.center();
+    Some((x as u16, y as u16))
+}
+
+// Full repaint: background, tiles, entities and HUD. Used at boot, whenever a menu
+// covered the play field and when the camera jumped.
+fn draw_scene<'d, SPI, DC>(
+    monitor: &mut LcdDisplay<'d, SPI, DC>,
+    camera: &Camera,
+    tiles: &TileGrid,
+    entities: &mut EntityManager,
+    hud: &mut Hud,
+) -> Result<(), DisplayError>
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    LcdMonitor::fill_monitor(monitor, BACKGROUND_COLOR)?;
+    camera.apply(monitor)?;
+
+    for tile in tiles.solid_tiles() {
+        camera.fill(tile, TILE_COLOR, monitor)?;
+    }
+
+    entities.render(camera, monitor)?;
+    entities.flush(camera, monitor)?;
+
+    hud.invalidate();
+    hud.flush(monitor)
+}
+
+// Paints the strip the camera just scrolled into view, then scrolls the panel over it.
+// The HUD is in the fixed area and stays as it is.
+fn draw_strip<'d, SPI, DC>(
+    monitor: &mut LcdDisplay<'d, SPI, DC>,
+    camera: &Camera,
+    tiles: &TileGrid,
+    entities: &mut EntityManager,
+    strip: Aabb,
+) -> Result<(), DisplayError>
+where
+    SPI: embedded_hal::spi::SpiDevice,
+    DC: embedded_hal::digital::OutputPin,
+{
+    camera.fill(strip, BACKGROUND_COLOR, monitor)?;
+    for tile in tiles.solid_tiles() {
+        if let Some(area) = tile.intersection(&strip) {
+            camera.fill(area, TILE_COLOR, monitor)?;
+        }
+    }
+    entities.redraw_overlapping(strip, camera, monitor)?;
+
+    camera.apply(monitor)
+}
@@ -0,0 +1,311 @@
+use alloc::{format, vec::Vec};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+
+use crate::{
+    inputs::{
+        NUMPAD_BUTTON_A, NUMPAD_BUTTON_B, NUMPAD_DOWN, NUMPAD_LEFT, NUMPAD_RIGHT, NUMPAD_SELECT,
+        NUMPAD_UP,
+    },
+    lcd::Canvas,
+    text::{draw_title, fonts::FONT_6X10},
+};
+
+mod pause;
+pub use pause::{PauseEvent, PauseMenu};
+
+const MENU_BACKGROUND: Rgb565 = Rgb565::CSS_DARK_SLATE_GRAY;
+const MENU_HIGHLIGHT: Rgb565 = Rgb565::CSS_STEEL_BLUE;
+const MENU_TEXT: Rgb565 = Rgb565::WHITE;
+
+const TITLE_Y: i32 = 6;
+const ITEMS_Y: i32 = 26;
+const ROW_HEIGHT: i32 = 16;
+const VALUE_X: i32 = 100;
+const SLIDER_WIDTH: u32 = 64;
+
+// `C` is whatever the callbacks get to change, `A` what selecting an item reports back.
+pub struct Menu<C, A> {
+    title: &'static str,
+    items: Vec<MenuItem<C, A>>,
+    cursor: usize,
+    back: Option<A>,
+    last_input: u8,
+    dirty: bool,
+}
+
+struct MenuItem<C, A> {
+    label: &'static str,
+    widget: Widget<C, A>,
+}
+
+enum Widget<C, A> {
+    Button {
+        on_select: fn(&mut C) -> Option<A>,
+    },
+    Slider {
+        value: u8,
+        min: u8,
+        max: u8,
+        step: u8,
+        on_change: fn(&mut C, u8),
+    },
+    Choice {
+        options: &'static [&'static str],
+        selected: usize,
+        on_change: fn(&mut C, usize),
+    },
+}
+
+impl<C, A: Copy> Menu<C, A> {
+    pub fn new(title: &'static str) -> Self {
+        Menu {
+            title,
+            items: Vec::new(),
+            cursor: 0,
+            back: None,
+            last_input: 0,
+            dirty: true,
+        }
+    }
+
+    pub fn with_button(mut self, label: &'static str, on_select: fn(&mut C) -> Option<A>) -> Self {
+        self.items.push(MenuItem {
+            label,
+            widget: Widget::Button { on_select },
+        });
+        self
+    }
+
+    pub fn with_slider(
+        mut self,
+        label: &'static str,
+        value: u8,
+        range: (u8, u8),
+        step: u8,
+        on_change: fn(&mut C, u8),
+    ) -> Self {
+        self.items.push(MenuItem {
+            label,
+            widget: Widget::Slider {
+                value: value.clamp(range.0, range.1),
+                min: range.0,
+                max: range.1,
+                step,
+                on_change,
+            },
+        });
+        self
+    }
+
+    pub fn with_choice(
+        mut self,
+        label: &'static str,
+        options: &'static [&'static str],
+        selected: usize,
+        on_change: fn(&mut C, usize),
+    ) -> Self {
+        self.items.push(MenuItem {
+            label,
+            widget: Widget::Choice {
+                options,
+                selected: selected.min(options.len().saturating_sub(1)),
+                on_change,
+            },
+        });
+        self
+    }
+
+    // Reported when SELECT or B is pressed.
+    pub fn with_back(mut self, action: A) -> Self {
+        self.back = Some(action);
+        self
+    }
+
+    // Puts the cursor back on top. The button that opened the menu counts as held, so it
+    // does not trigger anything until released.
+    pub fn open(&mut self, held: u8) {
+        self.cursor = 0;
+        self.last_input = held;
+        self.dirty = true;
+    }
+
+    // Inputs are levels, only presses (changes) are acted upon.
+    pub fn handle_input(&mut self, input: u8, ctx: &mut C) -> Option<A> {
+        if input == self.last_input {
+            return None;
+        }
+        self.last_input = input;
+
+        match input {
+            NUMPAD_UP if !self.items.is_empty() => {
+                self.cursor = self.cursor.checked_sub(1).unwrap_or(self.items.len() - 1);
+                self.dirty = true;
+                None
+            }
+            NUMPAD_DOWN if !self.items.is_empty() => {
+                self.cursor = (self.cursor + 1) % self.items.len();
+                self.dirty = true;
+                None
+            }
+            NUMPAD_LEFT => {
+                self.adjust(false, ctx);
+                None
+            }
+            NUMPAD_RIGHT => {
+                self.adjust(true, ctx);
+                None
+            }
+            NUMPAD_BUTTON_A => self.select(ctx),
+            NUMPAD_SELECT | NUMPAD_BUTTON_B => self.back,
+            _ => None,
+        }
+    }
+
+    fn select(&mut self, ctx: &mut C) -> Option<A> {
+        match self.items.get(self.cursor).map(|item| &item.widget)? {
+            Widget::Button { on_select } => on_select(ctx),
+            // A on a choice cycles through the options.
+            Widget::Choice { .. } => {
+                self.adjust(true, ctx);
+                None
+            }
+            Widget::Slider { .. } => None,
+        }
+    }
+
+    fn adjust(&mut self, up: bool, ctx: &mut C) {
+        let Some(item) = self.items.get_mut(self.cursor) else {
+            return;
+        };
+
+        match &mut item.widget {
+            Widget::Slider {
+                value,
+                min,
+                max,
+                step,
+                on_change,
+            } => {
+                let new_value = if up {
+                    value.saturating_add(*step).min(*max)
+                } else {
+                    value.saturating_sub(*step).max(*min)
+                };
+                if new_value != *value {
+                    *value = new_value;
+                    on_change(ctx, new_value);
+                    self.dirty = true;
+                }
+            }
+            Widget::Choice {
+                options,
+                selected,
+                on_change,
+            } => {
+                if options.is_empty() {
+                    return;
+                }
+                *selected = if up {
+                    (*selected + 1) % options.len()
+                } else {
+                    selected.checked_sub(1).unwrap_or(options.len() - 1)
+                };
+                on_change(ctx, *selected);
+                self.dirty = true;
+            }
+            Widget::Button { .. } => {}
+        }
+    }
+
+    // Returns false when nothing changed since the last draw.
+    pub fn draw(&mut self, canvas: &mut Canvas) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        self.dirty = false;
+
+        let width = canvas.width() as u32;
+        canvas.clear(MENU_BACKGROUND).ok();
+        Rectangle::new(Point::zero(), canvas.size())
+            .into_styled(PrimitiveStyle::with_stroke(MENU_TEXT, 1))
+            .draw(canvas)
+            .ok();
+
+        draw_title(canvas, self.title, TITLE_Y, text_style(MENU_BACKGROUND));
+
+        for (index, item) in self.items.iter().enumerate() {
+            let y = ITEMS_Y + index as i32 * ROW_HEIGHT;
+            let background = if index == self.cursor {
+                MENU_HIGHLIGHT
+            } else {
+                MENU_BACKGROUND
+            };
+
+            Rectangle::new(Point::new(2, y), Size::new(width - 4, ROW_HEIGHT as u32))
+                .into_styled(PrimitiveStyle::with_fill(background))
+                .draw(canvas)
+                .ok();
+
+            let style = text_style(background);
+            Text::with_baseline(item.label, Point::new(8, y + 3), style, Baseline::Top)
+                .draw(canvas)
+                .ok();
+
+            match &item.widget {
+                Widget::Button { .. } => {}
+                Widget::Slider {
+                    value, min, max, ..
+                } => {
+                    let span = (*max - *min).max(1) as u32;
+                    let filled = SLIDER_WIDTH * (*value - *min) as u32 / span;
+
+                    Rectangle::new(Point::new(VALUE_X, y + 4), Size::new(SLIDER_WIDTH + 2, 8))
+                        .into_styled(PrimitiveStyle::with_stroke(MENU_TEXT, 1))
+                        .draw(canvas)
+                        .ok();
+                    Rectangle::new(Point::new(VALUE_X + 1, y + 5), Size::new(filled, 6))
+                        .into_styled(PrimitiveStyle::with_fill(MENU_TEXT))
+                        .draw(canvas)
+                        .ok();
+                    Text::with_baseline(
+                        &format!("{:>3}", value),
+                        Point::new(VALUE_X + SLIDER_WIDTH as i32 + 6, y + 3),
+                        style,
+                        Baseline::Top,
+                    )
+                    .draw(canvas)
+                    .ok();
+                }
+                Widget::Choice {
+                    options, selected, ..
+                } => {
+                    let option = options.get(*selected).copied().unwrap_or_default();
+                    Text::with_baseline(
+                        &format!("< {} >", option),
+                        Point::new(VALUE_X, y + 3),
+                        style,
+                        Baseline::Top,
+                    )
+                    .draw(canvas)
+                    .ok();
+                }
+            }
+        }
+
+        true
+    }
+}
+
+fn text_style(background: Rgb565) -> MonoTextStyle<'static, Rgb565> {
+    MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(MENU_TEXT)
+        .background_color(background)
+        .build()
+}
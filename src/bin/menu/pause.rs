@@ -0,0 +1,136 @@
+use crate::{
+    inputs::KeyMap,
+    lcd::{Canvas, DisplayError, LcdDisplay, Screen, Scroll},
+    settings::Settings,
+    utils::AllocError,
+};
+
+use super::{MENU_BACKGROUND, Menu};
+
+// Centered on the screen.
+const PAUSE_WIDTH: u16 = 200;
+const PAUSE_HEIGHT: u16 = 120;
+
+const KEY_MAPS: [&str; 2] = ["A fires", "B fires"];
+
+#[derive(Clone, Copy)]
+enum PauseAction {
+    Resume,
+    Restart,
+    OpenSettings,
+    Back,
+}
+
+// What the game loop has to act upon once the menu is done.
+#[derive(Clone, Copy, Debug)]
+pub enum PauseEvent {
+    Resume,
+    Restart,
+}
+
+enum Page {
+    Main,
+    Settings,
+}
+
+pub struct PauseMenu {
+    page: Page,
+    main: Menu<Settings, PauseAction>,
+    settings: Menu<Settings, PauseAction>,
+    x: u16,
+    canvas: Canvas,
+}
+
+impl PauseMenu {
+    pub fn new(screen: Screen, settings: &Settings) -> Result<Self, AllocError> {
+        let main = Menu::<Settings, PauseAction>::new("PAUSED")
+            .with_button("Resume", |_| Some(PauseAction::Resume))
+            .with_button("Settings", |_| Some(PauseAction::OpenSettings))
+            .with_button("Restart", |_| Some(PauseAction::Restart))
+            .with_back(PauseAction::Resume);
+
+        let settings_menu = Menu::<Settings, PauseAction>::new("SETTINGS")
+            .with_slider(
+                "Brightness",
+                settings.brightness,
+                (10, 100),
+                10,
+                |settings, value| settings.brightness = value,
+            )
+            .with_slider(
+                "Volume",
+                settings.volume,
+                (0, 100),
+                10,
+                |settings, value| settings.volume = value,
+            )
+            .with_choice(
+                "Keys",
+                &KEY_MAPS,
+                settings.key_map.index() as usize,
+                |settings, selected| settings.key_map = KeyMap::from_index(selected as u8),
+            )
+            .with_button("Back", |_| Some(PauseAction::Back))
+            .with_back(PauseAction::Back);
+
+        let (x, y) = screen.center(PAUSE_WIDTH, PAUSE_HEIGHT);
+        Ok(PauseMenu {
+            page: Page::Main,
+            main,
+            settings: settings_menu,
+            x,
+            canvas: Canvas::new(screen, x, y, PAUSE_WIDTH, PAUSE_HEIGHT, MENU_BACKGROUND)?,
+        })
+    }
+
+    pub fn open(&mut self, held: u8) {
+        self.page = Page::Main;
+        self.main.open(held);
+    }
+
+    pub fn handle_input(&mut self, input: u8, settings: &mut Settings) -> Option<PauseEvent> {
+        let action = match self.page {
+            Page::Main => self.main.handle_input(input, settings),
+            Page::Settings => self.settings.handle_input(input, settings),
+        };
+
+        match action? {
+            PauseAction::Resume => Some(PauseEvent::Resume),
+            PauseAction::Restart => Some(PauseEvent::Restart),
+            PauseAction::OpenSettings => {
+                self.page = Page::Settings;
+                self.settings.open(input);
+                None
+            }
+            PauseAction::Back => {
+                self.page = Page::Main;
+                self.main.open(input);
+                None
+            }
+        }
+    }
+
+    // Screen columns the menu covers, first and last.
+    pub fn rows(&self) -> (u16, u16) {
+        (self.x, self.x + PAUSE_WIDTH - 1)
+    }
+
+    pub fn draw<'d, SPI, DC>(
+        &mut self,
+        scroll: Scroll,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        let redrawn = match self.page {
+            Page::Main => self.main.draw(&mut self.canvas),
+            Page::Settings => self.settings.draw(&mut self.canvas),
+        };
+        if redrawn {
+            self.canvas.flush(scroll, display)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use alloc::vec::Vec;
+
+use super::{
+    ENCODING_RAW, ENTRY_SIZE, Entry, FORMAT_VERSION, HEADER_SIZE, Kind, MAGIC, NAME_LEN, Sound,
+    SpriteEncoding,
+};
+use crate::save::crc32;
+
+#[derive(Debug)]
+pub enum BuildError {
+    // Names have to fit `NAME_LEN` bytes.
+    NameTooLong,
+    DuplicateName,
+    TooManyEntries,
+    // The data does not match the given dimensions.
+    WrongSize,
+    // A sound without a row length or without any step that lasts a row.
+    NoLength,
+}
+
+impl Entry {
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes[0..16].copy_from_slice(&self.name);
+        bytes[16] = self.kind as u8;
+        bytes[17] = self.encoding;
+        bytes[18..20].copy_from_slice(&self.width.to_le_bytes());
+        bytes[20..22].copy_from_slice(&self.height.to_le_bytes());
+        bytes[22..26].copy_from_slice(&self.offset.to_le_bytes());
+        bytes[26..30].copy_from_slice(&self.len.to_le_bytes());
+        bytes[30..34].copy_from_slice(&self.crc.to_le_bytes());
+    }
+}
+
+// Collects assets and writes them out as a pack, for the host side packer.
+#[derive(Default)]
+pub struct PackBuilder {
+    entries: Vec<(Entry, Vec<u8>)>,
+}
+
+impl PackBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `pixels` are Rgb565 in panel order. The sprite is stored in whichever encoding
+    // comes out smallest.
+    pub fn add_sprite(
+        &mut self,
+        name: &str,
+        width: u16,
+        height: u16,
+        pixels: &[u16],
+    ) -> Result<(), BuildError> {
+        if pixels.len() != width as usize * height as usize {
+            return Err(BuildError::WrongSize);
+        }
+
+        let raw = pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_le_bytes())
+            .collect();
+        let mut encodings = alloc::vec![(SpriteEncoding::Raw, raw)];
+
+        let mut palette: Vec<u16> = Vec::new();
+        for &pixel in pixels {
+            if !palette.contains(&pixel) {
+                palette.push(pixel);
+            }
+        }
+        if palette.len() <= 256 {
+            let indices: Vec<u8> = pixels
+                .iter()
+                .map(|pixel| palette.iter().position(|color| color == pixel).unwrap_or(0) as u8)
+                .collect();
+
+            let mut header = alloc::vec![palette.len() as u8];
+            for color in &palette {
+                header.extend_from_slice(&color.to_le_bytes());
+            }
+            let with_palette = |data: &[u8]| [header.as_slice(), data].concat();
+
+            if palette.len() <= 16 {
+                let packed: Vec<u8> = indices
+                    .chunks(2)
+                    .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0))
+                    .collect();
+                encodings.push((SpriteEncoding::Indexed4, with_palette(&packed)));
+            }
+            encodings.push((SpriteEncoding::Indexed8, with_palette(&indices)));
+            encodings.push((SpriteEncoding::Rle, with_palette(&runs(&indices))));
+        }
+
+        // The first of equally small ones wins, raw and indexed decode faster.
+        let (encoding, payload) = encodings
+            .into_iter()
+            .min_by_key(|(_, payload)| payload.len())
+            .unwrap_or((SpriteEncoding::Raw, Vec::new()));
+        self.add(name, Kind::Sprite, encoding as u8, width, height, payload)
+    }
+
+    // `tiles` row by row as seen on screen, the top row first.
+    pub fn add_map(
+        &mut self,
+        name: &str,
+        width: u16,
+        height: u16,
+        tiles: &[u8],
+    ) -> Result<(), BuildError> {
+        if tiles.len() != width as usize * height as usize {
+            return Err(BuildError::WrongSize);
+        }
+        self.add(name, Kind::Map, ENCODING_RAW, width, height, tiles.to_vec())
+    }
+
+    pub fn add_sound(&mut self, name: &str, sound: &Sound) -> Result<(), BuildError> {
+        if !sound.has_length() {
+            return Err(BuildError::NoLength);
+        }
+        let mut payload = Vec::with_capacity(3 + sound.steps.len() * 3);
+        payload.extend_from_slice(&sound.row_ms.to_le_bytes());
+        payload.push(sound.volume);
+        for (frequency, rows) in &sound.steps {
+            payload.extend_from_slice(&frequency.to_le_bytes());
+            payload.push(*rows);
+        }
+        self.add(name, Kind::Sound, ENCODING_RAW, 0, 0, payload)
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        kind: Kind,
+        encoding: u8,
+        width: u16,
+        height: u16,
+        payload: Vec<u8>,
+    ) -> Result<(), BuildError> {
+        if name.len() > NAME_LEN {
+            return Err(BuildError::NameTooLong);
+        }
+        if self.entries.iter().any(|(entry, _)| entry.name() == name) {
+            return Err(BuildError::DuplicateName);
+        }
+        if self.entries.len() >= u16::MAX as usize {
+            return Err(BuildError::TooManyEntries);
+        }
+
+        let mut padded = [0u8; NAME_LEN];
+        padded[..name.len()].copy_from_slice(name.as_bytes());
+        let entry = Entry {
+            name: padded,
+            kind,
+            encoding,
+            width,
+            height,
+            // Filled in by `finish`, once all entries are known.
+            offset: 0,
+            len: payload.len() as u32,
+            crc: crc32(&payload),
+        };
+        self.entries.push((entry, payload));
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let mut offset = (HEADER_SIZE + self.entries.len() * ENTRY_SIZE) as u32;
+        let mut index = alloc::vec![0u8; self.entries.len() * ENTRY_SIZE];
+        for ((entry, payload), bytes) in self
+            .entries
+            .iter_mut()
+            .zip(index.chunks_exact_mut(ENTRY_SIZE))
+        {
+            entry.offset = offset;
+            entry.encode(bytes);
+            offset += payload.len() as u32;
+        }
+
+        let mut pack = Vec::with_capacity(offset as usize);
+        pack.extend_from_slice(&MAGIC);
+        pack.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        pack.extend_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        pack.extend_from_slice(&crc32(&index).to_le_bytes());
+        pack.extend_from_slice(&index);
+        for (_, payload) in &self.entries {
+            pack.extend_from_slice(payload);
+        }
+        pack
+    }
+}
+
+// Run length pairs of count and color index.
+fn runs(indices: &[u8]) -> Vec<u8> {
+    let mut runs: Vec<u8> = Vec::new();
+    for &index in indices {
+        match runs.as_mut_slice() {
+            [.., len, last] if *last == index && *len < u8::MAX => *len += 1,
+            _ => runs.extend_from_slice(&[1, index]),
+        }
+    }
+    runs
+}
@@ -0,0 +1,77 @@
+use core::slice;
+
+use crate::save::{FlashPartition, Storage};
+
+// The PRO CPU half of the flash MMU. Its first 64 entries map the 4MB data window at
+// `DROM_BASE` in pages of 64K, entries with `MMU_INVALID` set map nothing. The app's own
+// read only data is mapped from the start of the window by the bootloader.
+const MMU_TABLE: *const u32 = 0x3FF1_0000 as *const u32;
+const MMU_INVALID: u32 = 0x100;
+const DROM_BASE: u32 = 0x3F40_0000;
+const DROM_PAGES: usize = 64;
+const PAGE_SIZE: u32 = 0x1_0000;
+
+#[derive(Debug)]
+pub enum MapError {
+    // The MMU maps whole pages, the partition has to start on one.
+    Unaligned,
+    // Not enough free pages left in the data window.
+    NoRoom,
+    // The ROM refused the mapping.
+    Mmu(i32),
+}
+
+unsafe extern "C" {
+    fn cache_flash_mmu_set_rom(
+        cpu_no: u32,
+        pid: u32,
+        vaddr: u32,
+        paddr: u32,
+        psize: u32,
+        num: u32,
+    ) -> i32;
+    fn Cache_Read_Disable_rom(cpu_no: u32);
+    fn Cache_Flush_rom(cpu_no: u32);
+    fn Cache_Read_Enable_rom(cpu_no: u32);
+}
+
+// Maps `partition` into the data window for good and returns it as one slice. Reads go
+// through the flash cache, nothing gets copied into RAM. Only the PRO CPU gets the
+// mapping, the game never starts the APP CPU.
+pub fn map_partition(partition: &FlashPartition<'_>) -> Result<&'static [u8], MapError> {
+    let (offset, len) = (partition.offset(), partition.capacity());
+    if !offset.is_multiple_of(PAGE_SIZE) {
+        return Err(MapError::Unaligned);
+    }
+    let pages = len.div_ceil(PAGE_SIZE as usize);
+    if pages > DROM_PAGES {
+        return Err(MapError::NoRoom);
+    }
+
+    // From the end of the window, so the mapping stays clear of the app's data.
+    let is_free = |page: usize| unsafe { MMU_TABLE.add(page).read_volatile() } & MMU_INVALID != 0;
+    let first = (0..=DROM_PAGES - pages)
+        .rev()
+        .find(|&first| (first..first + pages).all(is_free))
+        .ok_or(MapError::NoRoom)?;
+
+    let address = DROM_BASE + first as u32 * PAGE_SIZE;
+    let result = critical_section::with(|_| set_mapping(address, offset, pages as u32));
+    if result != 0 {
+        return Err(MapError::Mmu(result));
+    }
+    // The pages stay mapped until the next reset and flash writes only go to the saves.
+    Ok(unsafe { slice::from_raw_parts(address as *const u8, len) })
+}
+
+// The flash cache is off while the MMU changes, so this has to run from RAM.
+#[esp_hal::ram]
+fn set_mapping(address: u32, offset: u32, pages: u32) -> i32 {
+    unsafe {
+        Cache_Read_Disable_rom(0);
+        Cache_Flush_rom(0);
+        let result = cache_flash_mmu_set_rom(0, 0, address, offset, PAGE_SIZE / 1024, pages);
+        Cache_Read_Enable_rom(0);
+        result
+    }
+}
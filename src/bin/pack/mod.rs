@@ -0,0 +1,302 @@
+// Asset pack: sprites, maps and sounds the firmware loads from the `assets` flash
+// partition instead of building them in code, so they can change without a rebuild.
+// Like `save`, only `core`/`alloc` is used outside of the backends, so the packer tool
+// includes this module.
+//
+//     header: magic "PACK", format u16, entry count u16, crc32 u32 of the index
+//     entry:  name [u8; 16] (zero padded), kind u8, encoding u8, width u16, height u16,
+//             offset u32, length u32, crc32 u32 of the payload
+//
+// The index follows the header, offsets count from the start of the pack. Payloads:
+//
+//     sprite, raw:     width * height Rgb565 u16
+//     sprite, indexed: palette, then one color index per pixel in 8 bit, or two per byte
+//                      in 4 bit (high nibble first)
+//     sprite, rle:     palette, then runs of length u8 (1 to 255) and color index u8
+//     map, raw:        width * height tile bytes, row by row as seen on screen, 0 is free
+//     sound, raw:      row ms u16, volume u8, then steps of frequency u16 and rows u8
+//
+// Sprite pixels are in panel order like `Canvas`. A palette is a color count u8 (0 means
+// 256) followed by the colors as Rgb565 u16.
+//
+// The firmware maps the whole partition into the data cache window, see `flash`, so the
+// pack is one byte slice and payloads are borrowed from it instead of copied into RAM.
+
+use alloc::vec::Vec;
+
+mod sprite;
+pub use sprite::{Pixels, Sprite, SpriteEncoding};
+
+#[cfg(target_os = "none")]
+mod flash;
+#[cfg(target_os = "none")]
+pub use flash::map_partition;
+
+#[cfg(not(target_os = "none"))]
+mod builder;
+#[cfg(not(target_os = "none"))]
+pub use builder::{BuildError, PackBuilder};
+
+pub const NAME_LEN: usize = 16;
+
+const MAGIC: [u8; 4] = *b"PACK";
+const FORMAT_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 34;
+
+#[derive(Debug)]
+pub enum PackError {
+    // No pack at all, e.g. the partition was never flashed.
+    NotAPack,
+    UnsupportedFormat(u16),
+    // The index or a payload does not match its CRC.
+    Corrupt,
+    // The index or a payload reaches past the end of the pack.
+    Truncated,
+    NotFound,
+    WrongKind,
+    // The entry has other dimensions than the caller asked for.
+    WrongSize,
+    // The payload does not decode, or uses an encoding this firmware does not know.
+    BadPayload,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Sprite = 1,
+    Map = 2,
+    Sound = 3,
+}
+
+impl Kind {
+    fn from_u8(kind: u8) -> Option<Kind> {
+        match kind {
+            1 => Some(Kind::Sprite),
+            2 => Some(Kind::Map),
+            3 => Some(Kind::Sound),
+            _ => None,
+        }
+    }
+}
+
+// Maps and sounds are always raw, sprites have `SpriteEncoding`s. Encodings are per
+// entry, so newer packers can add some without breaking old entries.
+pub const ENCODING_RAW: u8 = 0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    name: [u8; NAME_LEN],
+    pub kind: Kind,
+    pub encoding: u8,
+    pub width: u16,
+    pub height: u16,
+    offset: u32,
+    len: u32,
+    crc: u32,
+}
+
+impl Entry {
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    // Entries of unknown kinds come back as `None` and get skipped.
+    fn decode(bytes: &[u8]) -> Option<Entry> {
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let u32_at = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+
+        let mut name = [0u8; NAME_LEN];
+        name.copy_from_slice(&bytes[0..16]);
+        Some(Entry {
+            name,
+            kind: Kind::from_u8(bytes[16])?,
+            encoding: bytes[17],
+            width: u16_at(18),
+            height: u16_at(20),
+            offset: u32_at(22),
+            len: u32_at(26),
+            crc: u32_at(30),
+        })
+    }
+}
+
+// Tiles row by row, the top row first, as seen on screen.
+pub struct Map<'a> {
+    pub width: u16,
+    pub height: u16,
+    tiles: &'a [u8],
+}
+
+impl Map<'_> {
+    pub fn tile(&self, x: u16, y: u16) -> u8 {
+        self.tiles[y as usize * self.width as usize + x as usize]
+    }
+}
+
+// One tracker pattern, see `audio::Pattern`. Steps are frequency and rows.
+pub struct Sound {
+    pub row_ms: u16,
+    pub volume: u8,
+    pub steps: Vec<(u16, u8)>,
+}
+
+impl Sound {
+    // Whether a pass over the steps takes any time at all. The player loops patterns, one
+    // that takes none would keep it busy forever.
+    pub fn has_length(&self) -> bool {
+        self.row_ms > 0 && self.steps.iter().any(|&(_, rows)| rows > 0)
+    }
+}
+
+pub struct AssetPack<'a> {
+    bytes: &'a [u8],
+    entries: Vec<Entry>,
+}
+
+impl<'a> AssetPack<'a> {
+    // Checks the index and decodes it, payloads are only checked when loaded. `bytes` can
+    // be the whole partition, anything after the last payload is ignored.
+    pub fn open(bytes: &'a [u8]) -> Result<Self, PackError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(PackError::NotAPack)?;
+        if header[0..4] != MAGIC {
+            return Err(PackError::NotAPack);
+        }
+        let format = u16::from_le_bytes([header[4], header[5]]);
+        if format != FORMAT_VERSION {
+            return Err(PackError::UnsupportedFormat(format));
+        }
+        let count = u16::from_le_bytes([header[6], header[7]]) as usize;
+        let crc = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+
+        // Checked in place, nothing gets allocated for an index that is not there.
+        let index = bytes
+            .get(HEADER_SIZE..HEADER_SIZE + count * ENTRY_SIZE)
+            .ok_or(PackError::Truncated)?;
+        if crate::save::crc32(index) != crc {
+            return Err(PackError::Corrupt);
+        }
+
+        let entries = index
+            .chunks_exact(ENTRY_SIZE)
+            .filter_map(Entry::decode)
+            .collect();
+        Ok(AssetPack { bytes, entries })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn find(&self, name: &str) -> Option<Entry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name() == name)
+            .copied()
+    }
+
+    // The payload of `entry`, checked against its CRC.
+    pub fn payload(&self, entry: &Entry) -> Result<&'a [u8], PackError> {
+        let start = entry.offset as usize;
+        let payload = start
+            .checked_add(entry.len())
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or(PackError::Truncated)?;
+        if crate::save::crc32(payload) != entry.crc {
+            return Err(PackError::Corrupt);
+        }
+        Ok(payload)
+    }
+
+    fn load(&self, name: &str, kind: Kind) -> Result<(Entry, &'a [u8]), PackError> {
+        let entry = self.find(name).ok_or(PackError::NotFound)?;
+        if entry.kind != kind {
+            return Err(PackError::WrongKind);
+        }
+        let payload = self.payload(&entry)?;
+        Ok((entry, payload))
+    }
+
+    // The sprite, still encoded. It has to be `width` by `height`.
+    pub fn sprite(&self, name: &str, width: u16, height: u16) -> Result<Sprite<'a>, PackError> {
+        let (entry, payload) = self.load(name, Kind::Sprite)?;
+        if entry.width != width || entry.height != height {
+            return Err(PackError::WrongSize);
+        }
+
+        let encoding = SpriteEncoding::from_u8(entry.encoding).ok_or(PackError::BadPayload)?;
+        let (palette, data) = match encoding {
+            SpriteEncoding::Raw => (Vec::new(), payload),
+            _ => split_palette(payload).ok_or(PackError::BadPayload)?,
+        };
+        if !encoding.check(&palette, data, width as usize * height as usize) {
+            return Err(PackError::BadPayload);
+        }
+
+        Ok(Sprite {
+            width,
+            height,
+            encoding,
+            palette,
+            data,
+        })
+    }
+
+    pub fn map(&self, name: &str) -> Result<Map<'a>, PackError> {
+        let (entry, tiles) = self.load(name, Kind::Map)?;
+        if entry.encoding != ENCODING_RAW
+            || tiles.len() != entry.width as usize * entry.height as usize
+        {
+            return Err(PackError::BadPayload);
+        }
+        Ok(Map {
+            width: entry.width,
+            height: entry.height,
+            tiles,
+        })
+    }
+
+    pub fn sound(&self, name: &str) -> Result<Sound, PackError> {
+        let (entry, payload) = self.load(name, Kind::Sound)?;
+        if entry.encoding != ENCODING_RAW || payload.len() < 3 || (payload.len() - 3) % 3 != 0 {
+            return Err(PackError::BadPayload);
+        }
+        let sound = Sound {
+            row_ms: u16::from_le_bytes([payload[0], payload[1]]),
+            volume: payload[2],
+            steps: payload[3..]
+                .chunks_exact(3)
+                .map(|step| (u16::from_le_bytes([step[0], step[1]]), step[2]))
+                .collect(),
+        };
+        if !sound.has_length() {
+            return Err(PackError::BadPayload);
+        }
+        Ok(sound)
+    }
+}
+
+// Splits a sprite payload into its palette and the pixel data.
+fn split_palette(payload: &[u8]) -> Option<(Vec<u16>, &[u8])> {
+    let (&count, rest) = payload.split_first()?;
+    let colors = if count == 0 { 256 } else { count as usize };
+    if rest.len() < colors * 2 {
+        return None;
+    }
+    let (palette, data) = rest.split_at(colors * 2);
+    let palette = palette
+        .chunks_exact(2)
+        .map(|color| u16::from_le_bytes([color[0], color[1]]))
+        .collect();
+    Some((palette, data))
+}
@@ -0,0 +1,137 @@
+use alloc::vec::Vec;
+
+// How the pixels of a sprite are stored. All but `Raw` index into a palette of Rgb565
+// colors, see the payload formats in `pack`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpriteEncoding {
+    Raw = 0,
+    Indexed8 = 1,
+    Indexed4 = 2,
+    Rle = 3,
+}
+
+impl SpriteEncoding {
+    pub fn from_u8(encoding: u8) -> Option<SpriteEncoding> {
+        match encoding {
+            0 => Some(SpriteEncoding::Raw),
+            1 => Some(SpriteEncoding::Indexed8),
+            2 => Some(SpriteEncoding::Indexed4),
+            3 => Some(SpriteEncoding::Rle),
+            _ => None,
+        }
+    }
+
+    // Whether `data` holds exactly `len` pixels with valid palette indices, so decoding
+    // it with `Pixels` cannot go out of bounds.
+    pub fn check(self, palette: &[u16], data: &[u8], len: usize) -> bool {
+        let colors = palette.len();
+        match self {
+            SpriteEncoding::Raw => data.len() == len * 2,
+            SpriteEncoding::Indexed8 => {
+                data.len() == len && data.iter().all(|&index| (index as usize) < colors)
+            }
+            SpriteEncoding::Indexed4 => {
+                data.len() == len.div_ceil(2)
+                    && (0..len).all(|pixel| (nibble(data, pixel) as usize) < colors)
+            }
+            SpriteEncoding::Rle => {
+                data.len().is_multiple_of(2)
+                    && data
+                        .chunks_exact(2)
+                        .all(|run| run[0] > 0 && (run[1] as usize) < colors)
+                    && data
+                        .chunks_exact(2)
+                        .map(|run| run[0] as usize)
+                        .sum::<usize>()
+                        == len
+            }
+        }
+    }
+}
+
+// High nibble first.
+fn nibble(data: &[u8], pixel: usize) -> u8 {
+    let byte = data[pixel / 2];
+    if pixel.is_multiple_of(2) {
+        byte >> 4
+    } else {
+        byte & 0x0F
+    }
+}
+
+// A sprite as loaded from a pack, still encoded. The pixel data is borrowed from the pack.
+pub struct Sprite<'a> {
+    pub width: u16,
+    pub height: u16,
+    pub encoding: SpriteEncoding,
+    pub palette: Vec<u16>,
+    pub data: &'a [u8],
+}
+
+// Decodes a sprite pixel by pixel into raw Rgb565, so nothing has to be unpacked into a
+// buffer first. The data has to pass `SpriteEncoding::check`.
+pub struct Pixels<'a> {
+    encoding: SpriteEncoding,
+    palette: &'a [u16],
+    data: &'a [u8],
+    remaining: usize,
+    // Next pixel, or next byte for RLE.
+    position: usize,
+    run: (u16, u8),
+}
+
+impl<'a> Pixels<'a> {
+    pub fn new(encoding: SpriteEncoding, palette: &'a [u16], data: &'a [u8], len: usize) -> Self {
+        Pixels {
+            encoding,
+            palette,
+            data,
+            remaining: len,
+            position: 0,
+            run: (0, 0),
+        }
+    }
+}
+
+impl Iterator for Pixels<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let position = self.position;
+        let color = match self.encoding {
+            SpriteEncoding::Raw => {
+                self.position += 2;
+                u16::from_le_bytes([self.data[position], self.data[position + 1]])
+            }
+            SpriteEncoding::Indexed8 => {
+                self.position += 1;
+                self.palette[self.data[position] as usize]
+            }
+            SpriteEncoding::Indexed4 => {
+                self.position += 1;
+                self.palette[nibble(self.data, position) as usize]
+            }
+            SpriteEncoding::Rle => {
+                if self.run.1 == 0 {
+                    let (len, index) = (self.data[position], self.data[position + 1]);
+                    self.position += 2;
+                    self.run = (self.palette[index as usize], len);
+                }
+                self.run.1 -= 1;
+                self.run.0
+            }
+        };
+        Some(color)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Pixels<'_> {}
@@ -0,0 +1,28 @@
+// CRC-32 as used by zip and ethernet (reflected, polynomial 0xEDB88320). Bitwise, the
+// saves are a few dozen bytes and a table would cost a kilobyte.
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32(!0)
+    }
+
+    pub fn update(mut self, bytes: &[u8]) -> Self {
+        for &byte in bytes {
+            self.0 ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (self.0 & 1).wrapping_neg();
+                self.0 = (self.0 >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+        self
+    }
+
+    pub fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    Crc32::new().update(bytes).finish()
+}
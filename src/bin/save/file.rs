@@ -0,0 +1,64 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use super::Storage;
+
+// A save partition image in a plain file, for working with saves on the host. New
+// files start out like erased flash.
+pub struct FileStorage {
+    file: File,
+    capacity: usize,
+}
+
+impl FileStorage {
+    pub fn open(path: &Path, capacity: usize) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let len = file.metadata()?.len() as usize;
+        if len < capacity {
+            file.seek(SeekFrom::Start(len as u64))?;
+            file.write_all(&vec![0xFF; capacity - len])?;
+        }
+
+        Ok(FileStorage { file, capacity })
+    }
+
+    fn check_bounds(&self, offset: u32, len: usize) -> io::Result<()> {
+        if offset as usize + len > self.capacity {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "access past the end of the storage",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Storage for FileStorage {
+    type Error = io::Error;
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_bounds(offset, bytes.len())?;
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.read_exact(bytes)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check_bounds(offset, bytes.len())?;
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.write_all(bytes)?;
+        self.file.flush()
+    }
+}
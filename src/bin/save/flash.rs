@@ -0,0 +1,70 @@
+use embedded_storage::{ReadStorage, Storage as _};
+use esp_bootloader_esp_idf::partitions::{self, PARTITION_TABLE_MAX_LEN};
+use esp_storage::{FlashStorage, FlashStorageError};
+
+use super::Storage;
+
+// A data partition of the SPI flash, found by its label in the partition table.
+pub struct FlashPartition<'d> {
+    flash: FlashStorage<'d>,
+    offset: u32,
+    len: u32,
+}
+
+#[derive(Debug)]
+pub enum PartitionError {
+    // The partition table could not be read or is corrupt.
+    Table,
+    NotFound,
+    ReadOnly,
+}
+
+impl<'d> FlashPartition<'d> {
+    pub fn find(mut flash: FlashStorage<'d>, label: &str) -> Result<Self, PartitionError> {
+        let mut table = [0u8; PARTITION_TABLE_MAX_LEN];
+        let partitions = partitions::read_partition_table(&mut flash, &mut table)
+            .map_err(|_| PartitionError::Table)?;
+
+        let partition = partitions
+            .iter()
+            .find(|partition| partition.label_as_str() == label)
+            .ok_or(PartitionError::NotFound)?;
+        if partition.is_read_only() {
+            return Err(PartitionError::ReadOnly);
+        }
+
+        let (offset, len) = (partition.offset(), partition.len());
+        Ok(FlashPartition { flash, offset, len })
+    }
+
+    // Where the partition starts in flash.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    fn address(&self, offset: u32, len: usize) -> Result<u32, FlashStorageError> {
+        if offset as u64 + len as u64 > self.len as u64 {
+            return Err(FlashStorageError::OutOfBounds);
+        }
+        Ok(self.offset + offset)
+    }
+}
+
+impl Storage for FlashPartition<'_> {
+    type Error = FlashStorageError;
+
+    fn capacity(&self) -> usize {
+        self.len as usize
+    }
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let address = self.address(offset, bytes.len())?;
+        self.flash.read(address, bytes)
+    }
+
+    // Erases and rewrites whole sectors, keeping whatever else they hold.
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let address = self.address(offset, bytes.len())?;
+        self.flash.write(address, bytes)
+    }
+}
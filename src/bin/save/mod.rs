@@ -0,0 +1,302 @@
+// Persistent save data. Like `audio`, only `core` is used outside of the backends, so the
+// host tools can include this module to read and write save images.
+//
+// The storage is split into two slots of one flash sector each. Saving always writes the
+// slot that does not hold the newest save, so a reset halfway through a write leaves the
+// previous save intact and erases are spread over both sectors.
+//
+// A slot is a header followed by records:
+//
+//     header: magic "SAVE", format u16, body length u16, sequence u32, crc32 u32
+//     record: tag u8, version u8, payload length u16, payload
+//
+// The CRC covers the header up to itself plus the body. Records with an unknown tag or
+// version are skipped, so older firmware can still read newer saves and vice versa.
+
+mod crc;
+pub use crc::crc32;
+
+#[cfg(target_os = "none")]
+mod flash;
+#[cfg(target_os = "none")]
+pub use flash::FlashPartition;
+
+#[cfg(not(target_os = "none"))]
+mod file;
+#[cfg(not(target_os = "none"))]
+pub use file::FileStorage;
+
+pub const SLOT_SIZE: u32 = 4096;
+pub const SLOTS: u32 = 2;
+pub const HIGH_SCORES: usize = 5;
+
+const MAGIC: [u8; 4] = *b"SAVE";
+const FORMAT_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 16;
+// Far more than the records need, keeps the buffers on the stack.
+const MAX_BODY: usize = 256;
+
+const TAG_HIGH_SCORES: u8 = 1;
+const TAG_KEY_MAP: u8 = 2;
+const TAG_BRIGHTNESS: u8 = 3;
+const TAG_VOLUME: u8 = 4;
+
+// Byte addressed storage the save slots live in, offsets are relative to its start.
+// Writes must take care of erasing the flash themselves.
+pub trait Storage {
+    type Error: core::fmt::Debug;
+
+    fn capacity(&self) -> usize;
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error>;
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug)]
+pub enum SaveError<E> {
+    Storage(E),
+    // The storage cannot hold both slots.
+    TooSmall,
+    // Neither slot holds a valid save, e.g. on first boot.
+    NoSave,
+}
+
+// Highest scores first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HighScores {
+    scores: [u32; HIGH_SCORES],
+    len: usize,
+}
+
+impl HighScores {
+    // Returns the rank the score got, if it made it into the table.
+    pub fn insert(&mut self, score: u32) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        let rank = self.scores[..self.len]
+            .iter()
+            .position(|&other| score > other)
+            .unwrap_or(self.len);
+        if rank >= HIGH_SCORES {
+            return None;
+        }
+
+        self.scores.copy_within(rank..HIGH_SCORES - 1, rank + 1);
+        self.scores[rank] = score;
+        self.len = (self.len + 1).min(HIGH_SCORES);
+        Some(rank)
+    }
+
+    pub fn scores(&self) -> &[u32] {
+        &self.scores[..self.len]
+    }
+}
+
+// Everything that survives a reset. Key map is stored by index, brightness and volume
+// in percent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SaveData {
+    pub high_scores: HighScores,
+    pub key_map: u8,
+    pub brightness: u8,
+    pub volume: u8,
+}
+
+impl SaveData {
+    fn encode(&self, body: &mut Writer) {
+        let mut scores = [0u8; 1 + HIGH_SCORES * 4];
+        scores[0] = self.high_scores.len as u8;
+        for (chunk, score) in scores[1..].chunks_mut(4).zip(self.high_scores.scores()) {
+            chunk.copy_from_slice(&score.to_le_bytes());
+        }
+        body.record(TAG_HIGH_SCORES, 1, &scores[..1 + self.high_scores.len * 4]);
+        body.record(TAG_KEY_MAP, 1, &[self.key_map]);
+        body.record(TAG_BRIGHTNESS, 1, &[self.brightness]);
+        body.record(TAG_VOLUME, 1, &[self.volume]);
+    }
+
+    // Fields without a (readable) record keep their current value.
+    fn decode(&mut self, mut body: &[u8]) {
+        while body.len() >= 4 {
+            let (tag, version) = (body[0], body[1]);
+            let len = u16::from_le_bytes([body[2], body[3]]) as usize;
+            let Some(payload) = body.get(4..4 + len) else {
+                return;
+            };
+            body = &body[4 + len..];
+
+            match (tag, version, payload) {
+                (TAG_HIGH_SCORES, 1, [count, scores @ ..])
+                    if (*count as usize) <= HIGH_SCORES && scores.len() == *count as usize * 4 =>
+                {
+                    let mut high_scores = HighScores::default();
+                    for chunk in scores.chunks_exact(4) {
+                        high_scores
+                            .insert(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+                    }
+                    self.high_scores = high_scores;
+                }
+                (TAG_KEY_MAP, 1, [key_map]) => self.key_map = *key_map,
+                (TAG_BRIGHTNESS, 1, [brightness]) => self.brightness = (*brightness).min(100),
+                (TAG_VOLUME, 1, [volume]) => self.volume = (*volume).min(100),
+                _ => {}
+            }
+        }
+    }
+}
+
+struct Writer {
+    bytes: [u8; HEADER_SIZE + MAX_BODY],
+    len: usize,
+}
+
+impl Writer {
+    fn record(&mut self, tag: u8, version: u8, payload: &[u8]) {
+        let end = self.len + 4 + payload.len();
+        // Only reachable by growing the records past MAX_BODY.
+        assert!(end <= self.bytes.len(), "save records do not fit");
+
+        self.bytes[self.len] = tag;
+        self.bytes[self.len + 1] = version;
+        self.bytes[self.len + 2..self.len + 4]
+            .copy_from_slice(&(payload.len() as u16).to_le_bytes());
+        self.bytes[self.len + 4..end].copy_from_slice(payload);
+        self.len = end;
+    }
+}
+
+// A slot holding a valid save.
+#[derive(Clone, Copy)]
+struct Slot {
+    index: u32,
+    sequence: u32,
+    len: usize,
+    // CRC of the body alone, to skip writes that would change nothing.
+    body_crc: u32,
+}
+
+pub struct SaveSlots<S: Storage> {
+    storage: S,
+    newest: Option<Slot>,
+}
+
+impl<S: Storage> SaveSlots<S> {
+    pub fn new(storage: S) -> Result<Self, SaveError<S::Error>> {
+        if storage.capacity() < (SLOTS * SLOT_SIZE) as usize {
+            return Err(SaveError::TooSmall);
+        }
+        Ok(SaveSlots {
+            storage,
+            newest: None,
+        })
+    }
+
+    // Reads the newest valid save into `data`.
+    pub fn load(&mut self, data: &mut SaveData) -> Result<(), SaveError<S::Error>> {
+        let mut bodies = [[0u8; MAX_BODY]; SLOTS as usize];
+        let mut newest: Option<Slot> = None;
+
+        for index in 0..SLOTS {
+            let Some(slot) = self.read_slot(index, &mut bodies[index as usize])? else {
+                continue;
+            };
+            // Sequence numbers wrap, compare them by distance.
+            let newer = match newest {
+                Some(newest) => slot.sequence.wrapping_sub(newest.sequence) as i32 > 0,
+                None => true,
+            };
+            if newer {
+                newest = Some(slot);
+            }
+        }
+
+        let Some(slot) = newest else {
+            return Err(SaveError::NoSave);
+        };
+        data.decode(&bodies[slot.index as usize][..slot.len]);
+        self.newest = Some(slot);
+        Ok(())
+    }
+
+    // Writes `data` into the older slot. Returns false when the newest save already
+    // holds exactly this data and nothing got written.
+    pub fn save(&mut self, data: &SaveData) -> Result<bool, SaveError<S::Error>> {
+        let (index, sequence) = match self.newest {
+            Some(newest) => ((newest.index + 1) % SLOTS, newest.sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+
+        let mut writer = Writer {
+            bytes: [0; HEADER_SIZE + MAX_BODY],
+            len: HEADER_SIZE,
+        };
+        data.encode(&mut writer);
+        let len = writer.len - HEADER_SIZE;
+
+        let body_crc = crc32(&writer.bytes[HEADER_SIZE..writer.len]);
+        if self
+            .newest
+            .is_some_and(|newest| newest.body_crc == body_crc)
+        {
+            return Ok(false);
+        }
+
+        let header = &mut writer.bytes[..HEADER_SIZE];
+        header[0..4].copy_from_slice(&MAGIC);
+        header[4..6].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header[6..8].copy_from_slice(&(len as u16).to_le_bytes());
+        header[8..12].copy_from_slice(&sequence.to_le_bytes());
+        let crc = slot_crc(&writer.bytes[..12], &writer.bytes[HEADER_SIZE..writer.len]);
+        writer.bytes[12..16].copy_from_slice(&crc.to_le_bytes());
+
+        self.storage
+            .write(index * SLOT_SIZE, &writer.bytes[..writer.len])
+            .map_err(SaveError::Storage)?;
+
+        self.newest = Some(Slot {
+            index,
+            sequence,
+            len,
+            body_crc,
+        });
+        Ok(true)
+    }
+
+    fn read_slot(
+        &mut self,
+        index: u32,
+        body: &mut [u8; MAX_BODY],
+    ) -> Result<Option<Slot>, SaveError<S::Error>> {
+        let offset = index * SLOT_SIZE;
+        let mut header = [0u8; HEADER_SIZE];
+        self.storage
+            .read(offset, &mut header)
+            .map_err(SaveError::Storage)?;
+
+        let format = u16::from_le_bytes([header[4], header[5]]);
+        let len = u16::from_le_bytes([header[6], header[7]]) as usize;
+        if header[0..4] != MAGIC || format != FORMAT_VERSION || len > MAX_BODY {
+            return Ok(None);
+        }
+
+        self.storage
+            .read(offset + HEADER_SIZE as u32, &mut body[..len])
+            .map_err(SaveError::Storage)?;
+
+        let crc = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+        if slot_crc(&header[..12], &body[..len]) != crc {
+            return Ok(None);
+        }
+
+        Ok(Some(Slot {
+            index,
+            sequence: u32::from_le_bytes([header[8], header[9], header[10], header[11]]),
+            len,
+            body_crc: crc32(&body[..len]),
+        }))
+    }
+}
+
+fn slot_crc(header: &[u8], body: &[u8]) -> u32 {
+    crc::Crc32::new().update(header).update(body).finish()
+}
@@ -0,0 +1,37 @@
+use crate::{inputs::KeyMap, save::SaveData};
+
+// Everything the player can change from the settings menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    // Both in percent.
+    pub brightness: u8,
+    pub volume: u8,
+    pub key_map: KeyMap,
+}
+
+impl Settings {
+    pub fn from_save(save: &SaveData) -> Self {
+        Settings {
+            // The menu does not go below 10% either, keep the screen from staying dark.
+            brightness: save.brightness.max(10),
+            volume: save.volume,
+            key_map: KeyMap::from_index(save.key_map),
+        }
+    }
+
+    pub fn store(&self, save: &mut SaveData) {
+        save.brightness = self.brightness;
+        save.volume = self.volume;
+        save.key_map = self.key_map.index();
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            brightness: 100,
+            volume: 50,
+            key_map: KeyMap::Default,
+        }
+    }
+}
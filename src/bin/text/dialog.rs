@@ -0,0 +1,147 @@
+use alloc::string::String;
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+
+use crate::{
+    inputs::NUMPAD_BUTTON_A,
+    lcd::{Canvas, DisplayError, LcdDisplay, Screen, Scroll},
+    utils::AllocError,
+};
+
+use super::{
+    TextBox, Typewriter, draw_title,
+    fonts::{FONT_6X13_BOLD, GLCD_5X8},
+};
+
+const DIALOG_BACKGROUND: Rgb565 = Rgb565::CSS_MIDNIGHT_BLUE;
+const DIALOG_TEXT: Rgb565 = Rgb565::WHITE;
+const FRAMES_PER_GLYPH: u8 = 2;
+
+const PADDING: i32 = 8;
+const TITLE_HEIGHT: i32 = 20;
+
+// Boxed message with a title and a body that types itself out. A skips the typing,
+// once everything is shown A closes the dialog.
+pub struct Dialog {
+    canvas: Canvas,
+    title: &'static str,
+    text: String,
+    typewriter: Typewriter,
+    last_input: u8,
+    redraw: bool,
+}
+
+impl Dialog {
+    // Position and size in landscape screen coordinates.
+    pub fn new(
+        screen: Screen,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+    ) -> Result<Self, AllocError> {
+        Ok(Dialog {
+            canvas: Canvas::new(screen, x, y, width, height, DIALOG_BACKGROUND)?,
+            title: "",
+            text: String::new(),
+            typewriter: Typewriter::new(0, FRAMES_PER_GLYPH),
+            last_input: 0,
+            redraw: false,
+        })
+    }
+
+    pub fn show(&mut self, title: &'static str, text: &str) {
+        self.title = title;
+        self.text = String::from(text);
+        self.typewriter = Typewriter::new(self.body().glyph_count(&self.text), FRAMES_PER_GLYPH);
+        self.redraw = true;
+    }
+
+    fn body(&self) -> TextBox<'static> {
+        let area = Rectangle::new(
+            Point::new(PADDING, PADDING + TITLE_HEIGHT),
+            self.canvas.size() - Size::new(2 * PADDING as u32, (2 * PADDING + TITLE_HEIGHT) as u32),
+        );
+        TextBox::new(area, style(&GLCD_5X8))
+    }
+
+    // Whether all of the text is out.
+    pub fn is_done(&self) -> bool {
+        self.typewriter.is_done()
+    }
+
+    // Returns true once the dialog got dismissed.
+    pub fn handle_input(&mut self, input: u8) -> bool {
+        if input == self.last_input {
+            return false;
+        }
+        self.last_input = input;
+
+        if input != NUMPAD_BUTTON_A {
+            return false;
+        }
+        if self.typewriter.is_done() {
+            return true;
+        }
+
+        self.typewriter.skip();
+        self.redraw = true;
+        false
+    }
+
+    pub fn draw<'d, SPI, DC>(
+        &mut self,
+        scroll: Scroll,
+        display: &mut LcdDisplay<'d, SPI, DC>,
+    ) -> Result<(), DisplayError>
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        DC: embedded_hal::digital::OutputPin,
+    {
+        if self.redraw {
+            self.redraw = false;
+            self.draw_frame();
+            let shown = 0..self.typewriter.shown();
+            self.body().draw_glyphs(&self.text, shown, &mut self.canvas);
+            return self.canvas.flush(scroll, display);
+        }
+
+        let range = self.typewriter.tick();
+        if range.is_empty() {
+            return Ok(());
+        }
+        // Only the columns the new glyphs landed in go over the bus.
+        if let Some(area) = self.body().draw_glyphs(&self.text, range, &mut self.canvas) {
+            let from = area.top_left.x as u16;
+            self.canvas
+                .flush_columns(from, from + area.size.width as u16, scroll, display)?;
+        }
+        Ok(())
+    }
+
+    fn draw_frame(&mut self) {
+        self.canvas.clear(DIALOG_BACKGROUND).ok();
+        Rectangle::new(Point::new(1, 1), self.canvas.size() - Size::new(2, 2))
+            .into_styled(PrimitiveStyle::with_stroke(DIALOG_TEXT, 1))
+            .draw(&mut self.canvas)
+            .ok();
+        draw_title(
+            &mut self.canvas,
+            self.title,
+            PADDING,
+            style(&FONT_6X13_BOLD),
+        );
+    }
+}
+
+fn style(font: &'static MonoFont<'static>) -> MonoTextStyle<'static, Rgb565> {
+    MonoTextStyleBuilder::new()
+        .font(font)
+        .text_color(DIALOG_TEXT)
+        .background_color(DIALOG_BACKGROUND)
+        .build()
+}
@@ -0,0 +1,219 @@
+use core::ops::Range;
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+
+mod dialog;
+pub use dialog::Dialog;
+
+// Fonts converted from `assets/fonts` by build.rs, next to the embedded-graphics ones.
+pub mod fonts {
+    use embedded_graphics::{
+        geometry::Size,
+        image::ImageRaw,
+        mono_font::{DecorationDimensions, MonoFont, mapping::StrGlyphMapping},
+    };
+
+    pub use embedded_graphics::mono_font::ascii::{FONT_6X10, FONT_6X13_BOLD};
+
+    include!(concat!(env!("OUT_DIR"), "/fonts.rs"));
+}
+
+// Splits text into lines of at most `columns` characters, breaking on spaces where it
+// can and inside words where it has to. `\n` always starts a new line.
+pub struct WrapLines<'t> {
+    rest: Option<&'t str>,
+    columns: usize,
+}
+
+pub fn wrap(text: &str, columns: usize) -> WrapLines<'_> {
+    WrapLines {
+        rest: Some(text),
+        columns: columns.max(1),
+    }
+}
+
+impl<'t> Iterator for WrapLines<'t> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest?;
+        let (paragraph, after) = match rest.find('\n') {
+            Some(newline) => (&rest[..newline], Some(&rest[newline + 1..])),
+            None => (rest, None),
+        };
+
+        // Byte offset of the first character that no longer fits, if any.
+        let Some((overflow, ch)) = paragraph.char_indices().nth(self.columns) else {
+            self.rest = after;
+            return Some(paragraph);
+        };
+
+        let (line, next) = match paragraph[..overflow + ch.len_utf8()].rfind(' ') {
+            Some(space) if space > 0 => (&paragraph[..space], &rest[space + 1..]),
+            _ => (&paragraph[..overflow], &rest[overflow..]),
+        };
+        self.rest = Some(next.trim_start_matches(' '));
+        Some(line)
+    }
+}
+
+// Word wrapped text inside a rectangle. Characters are counted in glyphs as laid out,
+// so `draw_glyphs` can reveal text bit by bit without redrawing what is already there.
+pub struct TextBox<'f> {
+    area: Rectangle,
+    style: MonoTextStyle<'f, Rgb565>,
+    line_spacing: u32,
+}
+
+impl<'f> TextBox<'f> {
+    pub fn new(area: Rectangle, style: MonoTextStyle<'f, Rgb565>) -> Self {
+        TextBox {
+            area,
+            style,
+            line_spacing: 2,
+        }
+    }
+
+    fn font(&self) -> &MonoFont<'f> {
+        self.style.font
+    }
+
+    fn advance(&self) -> u32 {
+        self.font().character_size.width + self.font().character_spacing
+    }
+
+    fn line_height(&self) -> u32 {
+        self.font().character_size.height + self.line_spacing
+    }
+
+    pub fn columns(&self) -> usize {
+        ((self.area.size.width + self.font().character_spacing) / self.advance()) as usize
+    }
+
+    pub fn rows(&self) -> usize {
+        ((self.area.size.height + self.line_spacing) / self.line_height()) as usize
+    }
+
+    // Lines that fit the box, anything past the last row is dropped.
+    pub fn lines<'t>(&self, text: &'t str) -> impl Iterator<Item = &'t str> {
+        wrap(text, self.columns()).take(self.rows())
+    }
+
+    pub fn glyph_count(&self, text: &str) -> usize {
+        self.lines(text).map(|line| line.chars().count()).sum()
+    }
+
+    // Draws the glyphs with index in `range` and returns the area they cover.
+    pub fn draw_glyphs<D>(
+        &self,
+        text: &str,
+        range: Range<usize>,
+        target: &mut D,
+    ) -> Option<Rectangle>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let mut index = 0;
+        let mut drawn: Option<(Point, Point)> = None;
+        let mut buf = [0u8; 4];
+
+        for (row, line) in self.lines(text).enumerate() {
+            for (column, ch) in line.chars().enumerate() {
+                if range.contains(&index) {
+                    let position = self.area.top_left
+                        + Point::new(
+                            (column as u32 * self.advance()) as i32,
+                            (row as u32 * self.line_height()) as i32,
+                        );
+                    Text::with_baseline(
+                        ch.encode_utf8(&mut buf),
+                        position,
+                        self.style,
+                        Baseline::Top,
+                    )
+                    .draw(target)
+                    .ok();
+
+                    let end = position + self.font().character_size - Point::new(1, 1);
+                    drawn = Some(match drawn {
+                        Some((top_left, bottom_right)) => (
+                            top_left.component_min(position),
+                            bottom_right.component_max(end),
+                        ),
+                        None => (position, end),
+                    });
+                }
+                index += 1;
+            }
+        }
+
+        drawn.map(|(top_left, bottom_right)| Rectangle::with_corners(top_left, bottom_right))
+    }
+}
+
+// Reveals text one glyph at a time, `frames_per_glyph` game loop iterations apart.
+pub struct Typewriter {
+    shown: usize,
+    total: usize,
+    frames_per_glyph: u8,
+    frames: u8,
+}
+
+impl Typewriter {
+    pub fn new(total: usize, frames_per_glyph: u8) -> Self {
+        Typewriter {
+            shown: 0,
+            total,
+            frames_per_glyph: frames_per_glyph.max(1),
+            frames: 0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.shown >= self.total
+    }
+
+    pub fn shown(&self) -> usize {
+        self.shown
+    }
+
+    // Glyphs that became visible this frame.
+    pub fn tick(&mut self) -> Range<usize> {
+        if self.is_done() {
+            return self.shown..self.shown;
+        }
+
+        self.frames += 1;
+        if self.frames < self.frames_per_glyph {
+            return self.shown..self.shown;
+        }
+        self.frames = 0;
+        self.shown += 1;
+        self.shown - 1..self.shown
+    }
+
+    // Shows everything that is left at once.
+    pub fn skip(&mut self) {
+        self.shown = self.total;
+    }
+}
+
+// Centers `text` horizontally on the given row.
+pub fn draw_title<D>(target: &mut D, text: &str, y: i32, style: MonoTextStyle<'_, Rgb565>)
+where
+    D: DrawTarget<Color = Rgb565> + OriginDimensions,
+{
+    let advance = style.font.character_size.width + style.font.character_spacing;
+    let width =
+        (text.chars().count() as u32 * advance).saturating_sub(style.font.character_spacing);
+    let x = (target.size().width as i32 - width as i32) / 2;
+
+    Text::with_baseline(text, Point::new(x, y), style, Baseline::Top)
+        .draw(target)
+        .ok();
+}
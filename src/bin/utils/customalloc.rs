@@ -1,55 +1,114 @@
-use alloc::{slice, vec::Vec};
-use core::alloc::{Layout, LayoutError};
-use esp_alloc::HEAP;
+use alloc::vec::Vec;
+use core::{
+    alloc::{Allocator, Layout, LayoutError},
+    ptr::{self, NonNull},
+};
 
-pub fn vec_into_iram<T>(size: usize) -> Result<Vec<T>, LayoutError> {
-    let ptr = unsafe {
-        HEAP.alloc_caps(
-            esp_alloc::MemoryCapability::Internal.into(),
-            Layout::array::<T>(size)?,
-        )
-    };
+use super::{EspHeap, MemoryCapability, region::RegionAllocator};
 
-    assert!(!ptr.is_null(), "Vec in IRAM Allocation failed");
+#[derive(Debug)]
+pub enum AllocError {
+    Layout(LayoutError),
+    // No region with the capability had `size` bytes left in one piece.
+    OutOfMemory {
+        capability: MemoryCapability,
+        size: usize,
+    },
+}
+
+impl From<LayoutError> for AllocError {
+    fn from(e: LayoutError) -> Self {
+        AllocError::Layout(e)
+    }
+}
+
+// The esp-alloc heap restricted to one kind of memory, for `Vec<T, Psram>`, `Box<T, Iram>`
+// and anything else taking an allocator. Growing goes through the same allocator, so the
+// data never leaves that memory.
+pub trait Region: Allocator + Copy {
+    const CAPABILITY: MemoryCapability;
+
+    // `len` copies of `value`, with no spare capacity.
+    fn vec_from_elem<T: Clone>(self, value: T, len: usize) -> Result<Vec<T, Self>, AllocError> {
+        let mut vec = self.vec_with_capacity(len)?;
+        vec.resize(len, value);
+        Ok(vec)
+    }
 
-    Ok(unsafe { Vec::from_raw_parts(ptr as *mut T, 0, size) })
+    fn vec_from_slice<T: Clone>(self, values: &[T]) -> Result<Vec<T, Self>, AllocError> {
+        let mut vec = self.vec_with_capacity(values.len())?;
+        vec.extend_from_slice(values);
+        Ok(vec)
+    }
+
+    fn vec_with_capacity<T>(self, capacity: usize) -> Result<Vec<T, Self>, AllocError> {
+        let mut vec = Vec::new_in(self);
+        if vec.try_reserve_exact(capacity).is_err() {
+            return Err(AllocError::OutOfMemory {
+                capability: Self::CAPABILITY,
+                size: capacity.saturating_mul(size_of::<T>()),
+            });
+        }
+        Ok(vec)
+    }
 }
 
-pub fn vec_into_psram<T>(size: usize) -> Result<Vec<T>, LayoutError> {
-    let ptr = unsafe {
-        HEAP.alloc_caps(
-            esp_alloc::MemoryCapability::External.into(),
-            Layout::array::<T>(size)?,
-        )
-    };
+// Internal RAM, needed for anything the DMA reads.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Iram;
 
-    assert!(!ptr.is_null(), "Vec in PSRAM Allocation failed");
+// External PSRAM, for the big buffers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Psram;
 
-    Ok(unsafe { Vec::from_raw_parts(ptr as *mut T, 0, size) })
+impl Region for Iram {
+    const CAPABILITY: MemoryCapability = MemoryCapability::Internal;
 }
 
-pub fn buffer_into_iram<T>(size: usize) -> Result<*mut [T], LayoutError> {
-    let ptr = unsafe {
-        HEAP.alloc_caps(
-            esp_alloc::MemoryCapability::Internal.into(),
-            Layout::array::<T>(size)?,
-        )
-    };
+impl Region for Psram {
+    const CAPABILITY: MemoryCapability = MemoryCapability::External;
+}
 
-    assert!(!ptr.is_null(), "Buffer in IRAM Allocation failed");
+// Both go through `EspHeap`, the same as `RegionVec`.
+fn allocate(
+    capability: MemoryCapability,
+    layout: Layout,
+) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+    if layout.size() == 0 {
+        // Any aligned address does for empty blocks.
+        let dangling = ptr::without_provenance_mut::<u8>(layout.align());
+        return Ok(NonNull::slice_from_raw_parts(
+            unsafe { NonNull::new_unchecked(dangling) },
+            0,
+        ));
+    }
+    NonNull::new(EspHeap.allocate(capability, layout))
+        .map(|ptr| NonNull::slice_from_raw_parts(ptr, layout.size()))
+        .ok_or(core::alloc::AllocError)
+}
 
-    Ok(unsafe { slice::from_raw_parts_mut(ptr as *mut T, size) })
+unsafe fn deallocate(ptr: NonNull<u8>, layout: Layout) {
+    if layout.size() != 0 {
+        unsafe { EspHeap.deallocate(ptr.as_ptr(), layout) }
+    }
 }
 
-pub fn buffer_into_psram<T>(size: usize) -> Result<*mut [T], LayoutError> {
-    let ptr = unsafe {
-        HEAP.alloc_caps(
-            esp_alloc::MemoryCapability::External.into(),
-            Layout::array::<T>(size)?,
-        )
-    };
+unsafe impl Allocator for Iram {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        allocate(Self::CAPABILITY, layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { deallocate(ptr, layout) }
+    }
+}
 
-    assert!(!ptr.is_null(), "Buffer in PSRAM Allocation failed");
+unsafe impl Allocator for Psram {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        allocate(Self::CAPABILITY, layout)
+    }
 
-    Ok(unsafe { slice::from_raw_parts_mut(ptr as *mut T, size) })
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { deallocate(ptr, layout) }
+    }
 }
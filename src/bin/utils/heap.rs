@@ -0,0 +1,19 @@
+use core::alloc::Layout;
+use esp_alloc::{HEAP, MemoryCapability};
+
+use super::region::RegionAllocator;
+
+// The global esp-alloc heap, restricted to the regions with the asked for capability.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EspHeap;
+
+unsafe impl RegionAllocator for EspHeap {
+    fn allocate(&self, capability: MemoryCapability, layout: Layout) -> *mut u8 {
+        unsafe { HEAP.alloc_caps(capability.into(), layout) }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        // The heap finds the region from the address.
+        unsafe { core::alloc::GlobalAlloc::dealloc(&HEAP, ptr, layout) }
+    }
+}
@@ -0,0 +1,78 @@
+use esp_alloc::{HEAP, MemoryCapability};
+use esp_hal::time::{Duration, Instant};
+use log::info;
+
+#[derive(Default)]
+struct Usage {
+    size: usize,
+    used: usize,
+    high_water: usize,
+}
+
+impl Usage {
+    fn percent(&self) -> usize {
+        self.used * 100 / self.size.max(1)
+    }
+}
+
+// Logs heap usage for internal RAM and PSRAM. High-water marks are sampled, so only
+// peaks that last until the next `update` are caught.
+pub struct MemoryReport {
+    internal: Usage,
+    external: Usage,
+    interval: Duration,
+    last_report: Instant,
+}
+
+impl MemoryReport {
+    pub fn new(interval: Duration) -> Self {
+        MemoryReport {
+            internal: Usage::default(),
+            external: Usage::default(),
+            interval,
+            last_report: Instant::now(),
+        }
+    }
+
+    // Call once per frame.
+    pub fn update(&mut self) {
+        let stats = HEAP.stats();
+
+        let (mut internal, mut external) = ((0, 0), (0, 0));
+        for region in stats.region_stats.iter().flatten() {
+            let usage = if region.capabilities.contains(MemoryCapability::External) {
+                &mut external
+            } else {
+                &mut internal
+            };
+            usage.0 += region.size;
+            usage.1 += region.used;
+        }
+        for (usage, (size, used)) in [
+            (&mut self.internal, internal),
+            (&mut self.external, external),
+        ] {
+            usage.size = size;
+            usage.used = used;
+            usage.high_water = usage.high_water.max(used);
+        }
+
+        if self.last_report.elapsed() < self.interval {
+            return;
+        }
+        self.last_report = Instant::now();
+
+        info!(
+            "MEMORY internal: {}/{} bytes ({}%), peak {} | external: {}/{} bytes ({}%), peak {} | heap peak {}",
+            self.internal.used,
+            self.internal.size,
+            self.internal.percent(),
+            self.internal.high_water,
+            self.external.used,
+            self.external.size,
+            self.external.percent(),
+            self.external.high_water,
+            stats.max_usage,
+        );
+    }
+}
@@ -1,2 +1,10 @@
 pub mod customalloc;
-pub use customalloc::{buffer_into_iram, buffer_into_psram, vec_into_iram, vec_into_psram};
+mod heap;
+#[cfg(feature = "memory-report")]
+pub mod memory;
+mod region;
+pub use customalloc::{AllocError, Iram, Psram, Region};
+pub use heap::EspHeap;
+pub use region::RegionVec;
+
+use esp_alloc::MemoryCapability;
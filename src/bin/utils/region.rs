@@ -0,0 +1,205 @@
+use core::{
+    alloc::Layout,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+// Nothing in here touches the heap directly, `EspHeap` and `MemoryCapability` come from the
+// parent module, so the host tests can include this file with stand-ins for both.
+
+use super::{AllocError, EspHeap, MemoryCapability};
+
+// Where `RegionVec` gets its memory from. Only `EspHeap` exists on the device, the
+// trait is there so the container can run against a fake heap.
+//
+// # Safety
+// `allocate` must return null or a block that fits `layout` and stays valid until it is
+// passed to `deallocate`.
+#[allow(clippy::missing_safety_doc, reason = "documented above")]
+pub unsafe trait RegionAllocator {
+    fn allocate(&self, capability: MemoryCapability, layout: Layout) -> *mut u8;
+
+    // # Safety
+    // `ptr` must come from `allocate` on this allocator with the same layout.
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout);
+}
+
+fn allocate<T, A: RegionAllocator>(
+    alloc: &A,
+    capability: MemoryCapability,
+    layout: Layout,
+) -> Result<NonNull<T>, AllocError> {
+    if layout.size() == 0 {
+        return Ok(NonNull::dangling());
+    }
+    NonNull::new(alloc.allocate(capability, layout) as *mut T).ok_or(AllocError::OutOfMemory {
+        capability,
+        size: layout.size(),
+    })
+}
+
+// A `Vec` that only ever lives in the memory it was created for: growing allocates the
+// new buffer with the same capability instead of going through the global allocator.
+pub struct RegionVec<T, A: RegionAllocator = EspHeap> {
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    capability: MemoryCapability,
+    alloc: A,
+    _owns: PhantomData<T>,
+}
+
+impl<T> RegionVec<T> {
+    // Allocates nothing until space is reserved.
+    pub fn new(capability: MemoryCapability) -> Self {
+        Self::new_in(capability, EspHeap)
+    }
+
+    pub fn with_capacity(
+        capability: MemoryCapability,
+        capacity: usize,
+    ) -> Result<Self, AllocError> {
+        Self::with_capacity_in(capability, capacity, EspHeap)
+    }
+}
+
+impl<T, A: RegionAllocator> RegionVec<T, A> {
+    pub fn new_in(capability: MemoryCapability, alloc: A) -> Self {
+        RegionVec {
+            ptr: NonNull::dangling(),
+            len: 0,
+            capacity: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            capability,
+            alloc,
+            _owns: PhantomData,
+        }
+    }
+
+    pub fn with_capacity_in(
+        capability: MemoryCapability,
+        capacity: usize,
+        alloc: A,
+    ) -> Result<Self, AllocError> {
+        let mut vec = Self::new_in(capability, alloc);
+        vec.try_reserve_exact(capacity)?;
+        Ok(vec)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Hands `value` back when there is no room left.
+    pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
+        if self.len == self.capacity {
+            return Err(value);
+        }
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.ptr.as_ptr().add(len) }, self.len - len);
+        // Shrink first, a panicking drop then leaks the rest instead of dropping twice.
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), AllocError> {
+        let required = self.required(additional)?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+        self.grow(required)
+    }
+
+    fn required(&self, additional: usize) -> Result<usize, AllocError> {
+        self.len
+            .checked_add(additional)
+            .ok_or(AllocError::OutOfMemory {
+                capability: self.capability,
+                size: usize::MAX,
+            })
+    }
+
+    fn grow(&mut self, capacity: usize) -> Result<(), AllocError> {
+        let ptr = allocate::<T, A>(&self.alloc, self.capability, Layout::array::<T>(capacity)?)?;
+        unsafe { ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len) };
+        self.free();
+        self.ptr = ptr;
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    fn free(&mut self) {
+        if mem::size_of::<T>() == 0 || self.capacity == 0 {
+            return;
+        }
+        unsafe {
+            let layout = Layout::array::<T>(self.capacity).unwrap_unchecked();
+            self.alloc.deallocate(self.ptr.as_ptr() as *mut u8, layout);
+        }
+    }
+}
+
+impl<T, A: RegionAllocator> Drop for RegionVec<T, A> {
+    fn drop(&mut self) {
+        self.clear();
+        self.free();
+    }
+}
+
+impl<T, A: RegionAllocator> Deref for RegionVec<T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: RegionAllocator> DerefMut for RegionVec<T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'v, T, A: RegionAllocator> IntoIterator for &'v RegionVec<T, A> {
+    type Item = &'v T;
+    type IntoIter = slice::Iter<'v, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'v, T, A: RegionAllocator> IntoIterator for &'v mut RegionVec<T, A> {
+    type Item = &'v mut T;
+    type IntoIter = slice::IterMut<'v, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
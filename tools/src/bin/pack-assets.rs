@@ -0,0 +1,289 @@
+// Builds the asset pack the firmware loads from its `assets` partition, and lists packs.
+//
+//     cargo run --bin pack-assets -- build <source dir> <pack>
+//     cargo run --bin pack-assets -- list <pack>
+//
+// Flash a pack with `espflash write-bin 0x310000 assets.pack`, no firmware rebuild needed.
+//
+// Every file in the source dir becomes one entry named after the file stem:
+//
+//     .sprite  `size <width> <height>`, `color <char> <RRGGBB>` lines, then one row of
+//              `width` color chars per line, top row first. Stored raw, with a 4 or 8 bit
+//              palette or run length encoded, whichever is smallest
+//     .map     one line per tile row, top row first, `#` is a wall and `.` free floor
+//     .track   `row_ms <ms>`, `volume <percent>`, then one `<note> <rows>` step per line,
+//              notes like `C4` or `FS5`, `-` for a rest
+//
+// `#` starts a comment in sprites and tracks, and blank lines are skipped everywhere.
+
+extern crate alloc;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../../../src/bin/pack/mod.rs"]
+mod pack;
+// For the CRC.
+#[allow(dead_code, unused_imports)]
+#[path = "../../../src/bin/save/mod.rs"]
+mod save;
+
+use std::{collections::HashMap, env, fs, path::Path, process};
+
+use pack::{AssetPack, BuildError, Kind, PackBuilder, Sound, SpriteEncoding};
+
+const USAGE: &str = "usage: pack-assets build <source dir> <pack> | list <pack>";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+// Meaningful lines with their line number, comments and blank lines removed.
+fn lines(text: &str, comments: bool) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(move |(number, line)| {
+            let line = match line.split_once('#') {
+                Some((before, _)) if comments => before,
+                _ => line,
+            };
+            (number + 1, line.trim())
+        })
+        .filter(|(_, line)| !line.is_empty())
+}
+
+fn parse<T: std::str::FromStr>(value: Option<&str>, what: &str) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("expected {}", what))
+}
+
+// Rounds like embedded-graphics does, so `Rgb565::CSS_*` colors come out the same.
+fn rgb565(rgb: u32) -> u16 {
+    let channel = |shift: u32, max: u32| (((rgb >> shift) & 0xFF) * max + 127) / 255;
+    (channel(16, 31) << 11 | channel(8, 63) << 5 | channel(0, 31)) as u16
+}
+
+// Returns width, height and the pixels in panel order: the panel is driven in portrait,
+// so screen columns become panel rows, bottom pixel first.
+fn parse_sprite(text: &str) -> Result<(u16, u16, Vec<u16>), String> {
+    let mut size = None;
+    let mut colors = HashMap::new();
+    let mut rows: Vec<Vec<u16>> = Vec::new();
+
+    for (number, line) in lines(text, true) {
+        let at = |e: String| format!("line {}: {}", number, e);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("size") => {
+                let width: u16 = parse(words.next(), "a width").map_err(at)?;
+                let height: u16 = parse(words.next(), "a height").map_err(at)?;
+                size = Some((width, height));
+            }
+            Some("color") => {
+                let key = words
+                    .next()
+                    .and_then(|key| key.chars().next())
+                    .ok_or_else(|| at("expected a color char".into()))?;
+                let rgb = words
+                    .next()
+                    .and_then(|rgb| u32::from_str_radix(rgb, 16).ok())
+                    .ok_or_else(|| at("expected a RRGGBB color".into()))?;
+                colors.insert(key, rgb565(rgb));
+            }
+            _ => {
+                let row = line
+                    .chars()
+                    .map(|key| colors.get(&key).copied())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| at("unknown color char".into()))?;
+                rows.push(row);
+            }
+        }
+    }
+
+    let (width, height) = size.ok_or("missing size")?;
+    if rows.len() != height as usize || rows.iter().any(|row| row.len() != width as usize) {
+        return Err(format!("pixels do not match the size {}x{}", width, height));
+    }
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for x in 0..width as usize {
+        pixels.extend(rows.iter().rev().map(|row| row[x]));
+    }
+    Ok((width, height, pixels))
+}
+
+fn parse_map(text: &str) -> Result<(u16, u16, Vec<u8>), String> {
+    let rows: Vec<&str> = lines(text, false).map(|(_, line)| line).collect();
+    let width = rows.first().map_or(0, |row| row.len());
+    if rows.iter().any(|row| row.len() != width) {
+        return Err("rows differ in length".into());
+    }
+
+    let mut tiles = Vec::with_capacity(width * rows.len());
+    for (number, row) in rows.iter().enumerate() {
+        for tile in row.chars() {
+            tiles.push(match tile {
+                '#' => 1,
+                '.' => 0,
+                _ => return Err(format!("row {}: unknown tile {:?}", number + 1, tile)),
+            });
+        }
+    }
+    Ok((width as u16, rows.len() as u16, tiles))
+}
+
+// Equal tempered, from A4 = 440 Hz, rounded like `audio::notes`.
+fn note_frequency(note: &str) -> Option<u16> {
+    let mut chars = note.chars();
+    let semitone = match chars.next()? {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (sharp, octave) = match rest.strip_prefix('S') {
+        Some(octave) => (1, octave),
+        None => (0, rest),
+    };
+    let octave: i32 = octave.parse().ok()?;
+    let from_a4 = (octave - 4) * 12 + semitone + sharp - 9;
+    Some((440.0 * 2f64.powf(from_a4 as f64 / 12.0)).round() as u16)
+}
+
+fn parse_track(text: &str) -> Result<Sound, String> {
+    let (mut row_ms, mut volume) = (None, None);
+    let mut steps = Vec::new();
+
+    for (number, line) in lines(text, true) {
+        let at = |e: String| format!("line {}: {}", number, e);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("row_ms") => row_ms = Some(parse(words.next(), "milliseconds").map_err(at)?),
+            Some("volume") => volume = Some(parse(words.next(), "a percentage").map_err(at)?),
+            Some(note) => {
+                let frequency = match note {
+                    "-" => 0,
+                    _ => note_frequency(note).ok_or_else(|| at(format!("bad note {}", note)))?,
+                };
+                let rows = parse(words.next(), "a row count").map_err(at)?;
+                steps.push((frequency, rows));
+            }
+            None => {}
+        }
+    }
+
+    let row_ms = row_ms.ok_or("missing row_ms")?;
+    let volume: u8 = volume.ok_or("missing volume")?;
+    if volume > 100 {
+        return Err("volume above 100".into());
+    }
+    Ok(Sound {
+        row_ms,
+        volume,
+        steps,
+    })
+}
+
+fn build_error(e: BuildError) -> String {
+    format!("{:?}", e)
+}
+
+fn build(source: &Path, out: &Path) {
+    let mut files: Vec<_> = fs::read_dir(source)
+        .unwrap_or_else(|e| fail(&format!("Could not read {}: {}", source.display(), e)))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    // Same input, same pack.
+    files.sort();
+
+    let mut builder = PackBuilder::new();
+    for path in &files {
+        let (Some(name), Some(extension)) = (
+            path.file_stem().and_then(|name| name.to_str()),
+            path.extension().and_then(|extension| extension.to_str()),
+        ) else {
+            continue;
+        };
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(&format!("Could not read {}: {}", path.display(), e)));
+
+        let added = match extension {
+            "sprite" => parse_sprite(&text).and_then(|(width, height, pixels)| {
+                builder
+                    .add_sprite(name, width, height, &pixels)
+                    .map_err(build_error)
+            }),
+            "map" => parse_map(&text).and_then(|(width, height, tiles)| {
+                builder
+                    .add_map(name, width, height, &tiles)
+                    .map_err(build_error)
+            }),
+            "track" => parse_track(&text)
+                .and_then(|sound| builder.add_sound(name, &sound).map_err(build_error)),
+            _ => {
+                println!("Skipping {}", path.display());
+                continue;
+            }
+        };
+        if let Err(e) = added {
+            fail(&format!("{}: {}", path.display(), e));
+        }
+    }
+
+    let pack = builder.finish();
+    if let Err(e) = fs::write(out, &pack) {
+        fail(&format!("Could not write {}: {}", out.display(), e));
+    }
+    println!("{} ({} bytes)", out.display(), pack.len());
+    list(out);
+}
+
+fn list(path: &Path) {
+    let bytes = fs::read(path)
+        .unwrap_or_else(|e| fail(&format!("Could not read {}: {}", path.display(), e)));
+    let pack = AssetPack::open(bytes.as_slice())
+        .unwrap_or_else(|e| fail(&format!("Not a valid pack: {:?}", e)));
+
+    for entry in pack.entries().to_vec() {
+        // Decoding checks the payload CRC as well as its contents.
+        let (encoding, check) = match entry.kind {
+            Kind::Sprite => (
+                SpriteEncoding::from_u8(entry.encoding)
+                    .map_or_else(|| String::from("unknown"), |e| format!("{:?}", e)),
+                pack.sprite(entry.name(), entry.width, entry.height)
+                    .map(|_| ()),
+            ),
+            Kind::Map => (String::from("raw"), pack.map(entry.name()).map(|_| ())),
+            Kind::Sound => (String::from("raw"), pack.sound(entry.name()).map(|_| ())),
+        };
+        let size = match entry.kind {
+            Kind::Sound => String::new(),
+            _ => format!("{}x{}", entry.width, entry.height),
+        };
+        println!(
+            "  {:<16} {:<7} {:>7} {:<9} {:>6} bytes  {}",
+            entry.name(),
+            format!("{:?}", entry.kind),
+            size,
+            encoding,
+            entry.len(),
+            check.map_or_else(|e| format!("{:?}", e), |_| String::from("ok"))
+        );
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.as_slice() {
+        [command, source, out] if command == "build" => build(Path::new(source), Path::new(out)),
+        [command, pack] if command == "list" => list(Path::new(pack)),
+        _ => fail(USAGE),
+    }
+}
@@ -0,0 +1,114 @@
+// Renders the game music and sound effects to WAV files, using the firmware's own
+// audio engine with an offline backend.
+//
+//     cargo run --bin render-audio -- [out dir] [voices] [volume]
+//
+// With one voice it sounds like the buzzer on the device does.
+
+extern crate alloc;
+
+// Only part of the engine is needed here.
+#[allow(dead_code)]
+#[path = "../../../src/bin/audio/mod.rs"]
+mod audio;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use audio::{Audio, Sfx, THEME, WavWriter};
+
+const SAMPLE_RATE: u32 = 22050;
+// Same step as a game loop iteration that takes about a frame.
+const FRAME_MS: u32 = 16;
+const THEME_MS: u32 = 20_000;
+// Effects are padded so the end does not get cut.
+const EFFECT_TAIL_MS: u32 = 200;
+
+fn render(
+    out: &Path,
+    name: &str,
+    voices: usize,
+    volume: u8,
+    play: impl FnOnce(&mut Audio<WavWriter>),
+    ms: u32,
+) {
+    let mut audio = Audio::new(WavWriter::new(SAMPLE_RATE, voices));
+    audio.set_volume(volume);
+    play(&mut audio);
+
+    let mut elapsed = 0;
+    while elapsed < ms {
+        audio.update(FRAME_MS);
+        elapsed += FRAME_MS;
+    }
+
+    let path = out.join(format!("{}.wav", name));
+    let duration = audio.backend().duration_ms();
+    if let Err(e) = fs::write(&path, audio.into_backend().into_wav()) {
+        eprintln!("Could not write {}: {}", path.display(), e);
+        process::exit(1);
+    }
+    println!("{} ({} ms)", path.display(), duration);
+}
+
+fn effect_ms(sfx: Sfx) -> u32 {
+    let pattern = sfx.pattern();
+    let rows: u32 = pattern.steps.iter().map(|step| step.rows as u32).sum();
+    rows * pattern.row_ms as u32 + EFFECT_TAIL_MS
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let out = PathBuf::from(args.next().unwrap_or_else(|| String::from("audio")));
+    let voices = match args.next().map(|voices| voices.parse::<usize>()) {
+        None => 1,
+        Some(Ok(voices)) if voices > 0 => voices,
+        Some(_) => {
+            eprintln!("Voices must be a positive number");
+            process::exit(2);
+        }
+    };
+    let volume = match args.next().map(|volume| volume.parse::<u8>()) {
+        None => 100,
+        Some(Ok(volume)) if volume <= 100 => volume,
+        Some(_) => {
+            eprintln!("Volume must be between 0 and 100");
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = fs::create_dir_all(&out) {
+        eprintln!("Could not create {}: {}", out.display(), e);
+        process::exit(1);
+    }
+
+    render(
+        &out,
+        "theme",
+        voices,
+        volume,
+        |audio| audio.play_music(&THEME),
+        THEME_MS,
+    );
+
+    for (name, sfx) in [
+        ("shoot", Sfx::Shoot),
+        ("hurt", Sfx::Hurt),
+        ("heal", Sfx::Heal),
+        ("coin", Sfx::Coin),
+        ("enemy_killed", Sfx::EnemyKilled),
+        ("game_over", Sfx::GameOver),
+    ] {
+        render(
+            &out,
+            name,
+            voices,
+            volume,
+            |audio| audio.play_effect(sfx),
+            effect_ms(sfx),
+        );
+    }
+}
@@ -0,0 +1,90 @@
+// Reads and edits save partition images with the firmware's own save code.
+//
+//     cargo run --bin save-tool -- <image> show
+//     cargo run --bin save-tool -- <image> set [brightness=N] [volume=N] [keys=N] [score=N]...
+//
+// Images can be read off the device with `espflash read-flash 0x3f0000 0x2000 save.bin`
+// and written back with `espflash write-bin 0x3f0000 save.bin`. A missing image is
+// created blank, like freshly erased flash.
+
+// Only part of the save code is needed here.
+#[allow(dead_code)]
+#[path = "../../../src/bin/save/mod.rs"]
+mod save;
+
+use std::{env, path::Path, process};
+
+use save::{FileStorage, SLOT_SIZE, SLOTS, SaveData, SaveError, SaveSlots};
+
+const USAGE: &str =
+    "usage: save-tool <image> show | set [brightness=N] [volume=N] [keys=N] [score=N]...";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+fn show(data: &SaveData) {
+    println!("brightness: {}%", data.brightness);
+    println!("volume:     {}%", data.volume);
+    println!("keys:       {}", data.key_map);
+    println!("high scores:");
+    for (rank, score) in data.high_scores.scores().iter().enumerate() {
+        println!("  {}. {}", rank + 1, score);
+    }
+}
+
+fn set(data: &mut SaveData, assignment: &str) {
+    let Some((key, value)) = assignment.split_once('=') else {
+        fail(USAGE);
+    };
+    let Ok(value) = value.parse::<u32>() else {
+        fail(&format!("Not a number: {}", value));
+    };
+    let percent = || u8::try_from(value).ok().filter(|&v| v <= 100);
+
+    match key {
+        "brightness" => data.brightness = percent().unwrap_or_else(|| fail("Out of range")),
+        "volume" => data.volume = percent().unwrap_or_else(|| fail("Out of range")),
+        "keys" => data.key_map = u8::try_from(value).unwrap_or_else(|_| fail("Out of range")),
+        "score" => match data.high_scores.insert(value) {
+            Some(rank) => println!("{} is rank {}", value, rank + 1),
+            None => println!("{} did not make the table", value),
+        },
+        _ => fail(&format!("Unknown field: {}", key)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [image, command, assignments @ ..] = args.as_slice() else {
+        fail(USAGE);
+    };
+
+    let storage = FileStorage::open(Path::new(image), (SLOTS * SLOT_SIZE) as usize)
+        .unwrap_or_else(|e| fail(&format!("Could not open {}: {}", image, e)));
+    let mut saves = SaveSlots::new(storage).unwrap_or_else(|e| fail(&format!("{:?}", e)));
+
+    let mut data = SaveData::default();
+    match saves.load(&mut data) {
+        Ok(()) => {}
+        Err(SaveError::NoSave) => println!("No valid save in {}", image),
+        Err(e) => fail(&format!("Could not read {}: {:?}", image, e)),
+    }
+
+    match command.as_str() {
+        "show" => show(&data),
+        "set" => {
+            for assignment in assignments {
+                set(&mut data, assignment);
+            }
+            match saves.save(&data) {
+                Ok(true) => println!("Saved"),
+                Ok(false) => println!("Nothing changed"),
+                Err(e) => fail(&format!("Could not write {}: {:?}", image, e)),
+            }
+            show(&data);
+        }
+        _ => fail(USAGE),
+    }
+}
@@ -0,0 +1,75 @@
+// The firmware's audio engine, run on the host with a backend that just keeps the tones.
+
+extern crate alloc;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../../src/bin/audio/mod.rs"]
+mod audio;
+
+use audio::{Audio, AudioBackend, Pattern, Tone, Track, note, rest};
+
+struct Voices(Vec<Option<Tone>>);
+
+impl AudioBackend for Voices {
+    fn voices(&self) -> usize {
+        self.0.len()
+    }
+
+    fn set_voice(&mut self, voice: usize, tone: Option<Tone>) {
+        self.0[voice] = tone;
+    }
+}
+
+static STEPS: [audio::Step; 2] = [note(440, 2), rest(1)];
+static EMPTY_STEPS: [audio::Step; 2] = [note(440, 0), rest(0)];
+
+#[test]
+fn patterns_loop() {
+    static PATTERN: Pattern = Pattern {
+        steps: &STEPS,
+        row_ms: 10,
+        volume: 100,
+    };
+    static TRACK: Track = [&PATTERN, &PATTERN];
+
+    let mut audio = Audio::new(Voices(vec![None]));
+    audio.play_music(&TRACK);
+    let mut heard = Vec::new();
+    for _ in 0..7 {
+        audio.update(10);
+        heard.push(audio.backend().0[0].map(|tone| tone.frequency));
+    }
+    assert_eq!(
+        heard,
+        [
+            Some(440),
+            None,
+            Some(440),
+            Some(440),
+            None,
+            Some(440),
+            Some(440)
+        ]
+    );
+}
+
+#[test]
+fn patterns_without_length_stop() {
+    static NO_ROWS: Pattern = Pattern {
+        steps: &EMPTY_STEPS,
+        row_ms: 10,
+        volume: 100,
+    };
+    static NO_ROW_MS: Pattern = Pattern {
+        steps: &STEPS,
+        row_ms: 0,
+        volume: 100,
+    };
+    static TRACK: Track = [&NO_ROWS, &NO_ROW_MS];
+
+    let mut audio = Audio::new(Voices(vec![None, None]));
+    audio.play_music(&TRACK);
+    audio.update(0);
+    audio.update(16);
+    assert_eq!(audio.backend().0, [None, None]);
+}
@@ -0,0 +1,132 @@
+// The firmware's collision math, run on the host.
+
+extern crate alloc;
+
+#[allow(dead_code)]
+#[path = "../../src/bin/collision/mod.rs"]
+mod collision;
+
+use collision::{Aabb, SolidTiles, TILE_SIZE, TileGrid, collides_with_tiles, move_and_slide};
+
+#[test]
+fn overlapping_edges_intersect() {
+    let a = Aabb::new(0, 0, 10, 10);
+
+    assert!(a.intersects(&Aabb::new(9, 0, 10, 10)));
+    assert!(a.intersects(&Aabb::new(0, 9, 10, 10)));
+    assert!(a.intersects(&Aabb::new(-9, -9, 10, 10)));
+    assert_eq!(
+        a.intersection(&Aabb::new(9, 9, 10, 10)),
+        Some(Aabb::new(9, 9, 1, 1))
+    );
+}
+
+#[test]
+fn touching_boxes_do_not_intersect() {
+    let a = Aabb::new(0, 0, 10, 10);
+
+    for other in [
+        Aabb::new(10, 0, 10, 10),
+        Aabb::new(-10, 0, 10, 10),
+        Aabb::new(0, 10, 10, 10),
+        Aabb::new(0, -10, 10, 10),
+        Aabb::new(10, 10, 10, 10),
+    ] {
+        assert!(!a.intersects(&other), "{:?}", other);
+        assert!(!other.intersects(&a), "{:?}", other);
+        assert_eq!(a.intersection(&other), None);
+    }
+}
+
+#[test]
+fn union_covers_both() {
+    let a = Aabb::new(-5, 0, 10, 10);
+    let b = Aabb::new(20, 30, 4, 4);
+
+    assert_eq!(a.union(&b), Aabb::new(-5, 0, 29, 34));
+    assert_eq!(a.union(&a), a);
+}
+
+#[test]
+fn outside_the_grid_is_solid() {
+    let grid = TileGrid::new(2, 3);
+
+    assert!(!grid.is_solid(0, 0));
+    assert!(!grid.is_solid(1, 2));
+    assert!(grid.is_solid(-1, 0));
+    assert!(grid.is_solid(0, -1));
+    assert!(grid.is_solid(2, 0));
+    assert!(grid.is_solid(0, 3));
+}
+
+#[test]
+fn tile_lookups_at_map_bounds() {
+    let grid = TileGrid::new(2, 2);
+    let size = TILE_SIZE as i32;
+
+    // Flush against every edge, still inside.
+    assert!(!collides_with_tiles(
+        &Aabb::new(0, 0, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+    assert!(!collides_with_tiles(
+        &Aabb::new(size, size, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+    assert_eq!(grid.bounds(), Aabb::new(0, 0, TILE_SIZE * 2, TILE_SIZE * 2));
+
+    // One pixel out on either side.
+    assert!(collides_with_tiles(
+        &Aabb::new(-1, 0, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+    assert!(collides_with_tiles(
+        &Aabb::new(0, -1, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+    assert!(collides_with_tiles(
+        &Aabb::new(size + 1, 0, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+    assert!(collides_with_tiles(
+        &Aabb::new(0, size + 1, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+}
+
+#[test]
+fn solid_tiles_are_found() {
+    let mut grid = TileGrid::new(3, 3);
+    grid.set_solid(1, 2, true);
+    // Out of range, ignored.
+    grid.set_solid(3, 0, true);
+
+    let size = TILE_SIZE as i32;
+    let tiles: Vec<Aabb> = grid.solid_tiles().collect();
+    assert_eq!(tiles, [Aabb::new(size, 2 * size, TILE_SIZE, TILE_SIZE)]);
+    assert!(grid.is_solid(1, 2));
+
+    // Touching the tile is fine, one pixel into it is not.
+    assert!(!collides_with_tiles(
+        &Aabb::new(size, size, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+    assert!(collides_with_tiles(
+        &Aabb::new(size, size + 1, TILE_SIZE, TILE_SIZE),
+        &grid
+    ));
+}
+
+#[test]
+fn slides_along_walls() {
+    let grid = TileGrid::new(2, 2);
+    let start = Aabb::new(4, 4, 8, 8);
+
+    let (moved, hit) = move_and_slide(start, -10, 6, &grid);
+    assert_eq!(moved, Aabb::new(0, 10, 8, 8));
+    assert!(hit.x && !hit.y);
+
+    let (moved, hit) = move_and_slide(start, 3, 100, &grid);
+    assert_eq!(moved, Aabb::new(7, 2 * TILE_SIZE as i32 - 8, 8, 8));
+    assert!(!hit.x && hit.y);
+}
@@ -0,0 +1,158 @@
+// The firmware's asset pack reader, run on the host against packs from `PackBuilder`.
+
+extern crate alloc;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../../src/bin/pack/mod.rs"]
+mod pack;
+// For the CRC.
+#[allow(dead_code, unused_imports)]
+#[path = "../../src/bin/save/mod.rs"]
+mod save;
+
+use pack::{AssetPack, BuildError, PackBuilder, PackError, Sound};
+
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 34;
+
+fn sound(row_ms: u16, steps: &[(u16, u8)]) -> Sound {
+    Sound {
+        row_ms,
+        volume: 50,
+        steps: steps.to_vec(),
+    }
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+// Lets `edit` change the payload of an entry, then fixes up both CRCs so only the
+// contents are wrong.
+fn patch_payload(pack: &mut [u8], entry: usize, edit: impl FnOnce(&mut [u8])) {
+    let at = HEADER_SIZE + entry * ENTRY_SIZE;
+    let (offset, len) = (
+        u32_at(pack, at + 22) as usize,
+        u32_at(pack, at + 26) as usize,
+    );
+    edit(&mut pack[offset..offset + len]);
+
+    let crc = save::crc32(&pack[offset..offset + len]);
+    pack[at + 30..at + 34].copy_from_slice(&crc.to_le_bytes());
+    let count = u16::from_le_bytes([pack[6], pack[7]]) as usize;
+    let crc = save::crc32(&pack[HEADER_SIZE..HEADER_SIZE + count * ENTRY_SIZE]);
+    pack[8..12].copy_from_slice(&crc.to_le_bytes());
+}
+
+#[test]
+fn sounds_round_trip() {
+    let mut builder = PackBuilder::new();
+    builder
+        .add_sound("beep", &sound(100, &[(440, 0), (0, 2)]))
+        .unwrap();
+    let bytes = builder.finish();
+
+    let pack = AssetPack::open(bytes.as_slice()).unwrap();
+    let beep = pack.sound("beep").unwrap();
+    assert_eq!(beep.row_ms, 100);
+    assert_eq!(beep.steps, [(440, 0), (0, 2)]);
+}
+
+#[test]
+fn sounds_without_length_are_refused() {
+    let mut builder = PackBuilder::new();
+    for silent in [
+        sound(0, &[(440, 4)]),
+        sound(100, &[(440, 0), (0, 0)]),
+        sound(100, &[]),
+    ] {
+        assert!(matches!(
+            builder.add_sound("silent", &silent),
+            Err(BuildError::NoLength)
+        ));
+    }
+}
+
+#[test]
+fn sounds_without_length_do_not_load() {
+    let mut builder = PackBuilder::new();
+    builder.add_sound("rows", &sound(100, &[(440, 4)])).unwrap();
+    builder
+        .add_sound("row_ms", &sound(100, &[(440, 4)]))
+        .unwrap();
+    let mut bytes = builder.finish();
+
+    // No step lasts a row.
+    patch_payload(&mut bytes, 0, |payload| payload[5] = 0);
+    // Rows of no time.
+    patch_payload(&mut bytes, 1, |payload| payload[..2].fill(0));
+
+    let pack = AssetPack::open(bytes.as_slice()).unwrap();
+    assert!(matches!(pack.sound("rows"), Err(PackError::BadPayload)));
+    assert!(matches!(pack.sound("row_ms"), Err(PackError::BadPayload)));
+}
+
+fn one_sound_pack() -> Vec<u8> {
+    let mut builder = PackBuilder::new();
+    builder.add_sound("beep", &sound(100, &[(440, 4)])).unwrap();
+    builder.finish()
+}
+
+#[test]
+fn trailing_bytes_are_ignored() {
+    // Like the rest of the partition after the pack, erased flash.
+    let mut bytes = one_sound_pack();
+    bytes.resize(bytes.len() + 4096, 0xFF);
+
+    let pack = AssetPack::open(bytes.as_slice()).unwrap();
+    assert_eq!(pack.entries().len(), 1);
+    pack.sound("beep").unwrap();
+}
+
+#[test]
+fn index_is_bounded_by_the_pack() {
+    let mut bytes = one_sound_pack();
+    // An entry count far past what the bytes hold.
+    bytes[6..8].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(matches!(
+        AssetPack::open(bytes.as_slice()),
+        Err(PackError::Truncated)
+    ));
+
+    assert!(matches!(
+        AssetPack::open(&bytes[..HEADER_SIZE - 1]),
+        Err(PackError::NotAPack)
+    ));
+    assert!(matches!(
+        AssetPack::open(&[0xFF; 64][..]),
+        Err(PackError::NotAPack)
+    ));
+}
+
+#[test]
+fn corrupt_index_is_refused() {
+    let mut bytes = one_sound_pack();
+    bytes[HEADER_SIZE] ^= 0x01;
+    assert!(matches!(
+        AssetPack::open(bytes.as_slice()),
+        Err(PackError::Corrupt)
+    ));
+}
+
+#[test]
+fn payloads_are_bounded_by_the_pack() {
+    let mut bytes = one_sound_pack();
+    let end = bytes.len();
+    let pack = AssetPack::open(&bytes[..end - 1]).unwrap();
+    assert!(matches!(pack.sound("beep"), Err(PackError::Truncated)));
+
+    // Payloads are borrowed, not copied.
+    let pack = AssetPack::open(bytes.as_slice()).unwrap();
+    let entry = pack.find("beep").unwrap();
+    let payload = pack.payload(&entry).unwrap();
+    assert_eq!(payload.as_ptr_range().end, bytes.as_ptr_range().end);
+
+    bytes[end - 1] ^= 0x01;
+    let pack = AssetPack::open(bytes.as_slice()).unwrap();
+    assert!(matches!(pack.sound("beep"), Err(PackError::Corrupt)));
+}
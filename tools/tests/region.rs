@@ -0,0 +1,243 @@
+// The firmware's region container, run on the host against a heap that keeps count.
+
+use std::{
+    alloc::{self, Layout, LayoutError},
+    cell::RefCell,
+    rc::Rc,
+};
+
+#[allow(dead_code)]
+#[path = "../../src/bin/utils/region.rs"]
+mod region;
+
+use region::{RegionAllocator, RegionVec};
+
+// Stand-ins for what `src/bin/utils` gives the containers on the device. `customalloc`
+// also holds the `Allocator` based regions, which need nightly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryCapability {
+    Internal,
+    External,
+}
+
+#[derive(Debug)]
+pub enum AllocError {
+    Layout(LayoutError),
+    OutOfMemory {
+        capability: MemoryCapability,
+        size: usize,
+    },
+}
+
+impl From<LayoutError> for AllocError {
+    fn from(e: LayoutError) -> Self {
+        AllocError::Layout(e)
+    }
+}
+
+// Only there as the default allocator, every test passes its own.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EspHeap;
+
+unsafe impl RegionAllocator for EspHeap {
+    fn allocate(&self, _capability: MemoryCapability, _layout: Layout) -> *mut u8 {
+        std::ptr::null_mut()
+    }
+
+    unsafe fn deallocate(&self, _ptr: *mut u8, _layout: Layout) {}
+}
+
+#[derive(Default)]
+struct Counts {
+    // Every block handed out, in order.
+    allocated: Vec<(MemoryCapability, Layout)>,
+    freed: Vec<Layout>,
+    live: Vec<(usize, Layout)>,
+}
+
+// Backed by the host allocator, refuses anything bigger than `limit` bytes.
+struct CountingHeap {
+    limit: usize,
+    counts: RefCell<Counts>,
+}
+
+impl CountingHeap {
+    fn new() -> Self {
+        Self::with_limit(usize::MAX)
+    }
+
+    fn with_limit(limit: usize) -> Self {
+        CountingHeap {
+            limit,
+            counts: RefCell::new(Counts::default()),
+        }
+    }
+
+    fn allocated(&self) -> Vec<(MemoryCapability, Layout)> {
+        self.counts.borrow().allocated.clone()
+    }
+
+    fn freed(&self) -> Vec<Layout> {
+        self.counts.borrow().freed.clone()
+    }
+
+    fn live(&self) -> usize {
+        self.counts.borrow().live.len()
+    }
+}
+
+unsafe impl RegionAllocator for &CountingHeap {
+    fn allocate(&self, capability: MemoryCapability, layout: Layout) -> *mut u8 {
+        assert_ne!(layout.size(), 0, "zero sized blocks must not hit the heap");
+        if layout.size() > self.limit {
+            return std::ptr::null_mut();
+        }
+        let ptr = unsafe { alloc::alloc(layout) };
+        let mut counts = self.counts.borrow_mut();
+        counts.allocated.push((capability, layout));
+        counts.live.push((ptr as usize, layout));
+        ptr
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        let mut counts = self.counts.borrow_mut();
+        let Some(index) = counts
+            .live
+            .iter()
+            .position(|&(live, _)| live == ptr as usize)
+        else {
+            panic!("freeing a block that is not allocated");
+        };
+        let (_, allocated) = counts.live.remove(index);
+        assert_eq!(allocated, layout, "freed with a different layout");
+        counts.freed.push(layout);
+        unsafe { alloc::dealloc(ptr, layout) };
+    }
+}
+
+#[test]
+fn growing_stays_in_the_same_memory() {
+    let heap = CountingHeap::new();
+    let mut vec = RegionVec::new_in(MemoryCapability::Internal, &heap);
+
+    for value in 0..100u32 {
+        vec.try_reserve_exact(1).unwrap();
+        vec.push_within_capacity(value).unwrap();
+    }
+    assert!(vec.iter().copied().eq(0..100));
+
+    let allocated = heap.allocated();
+    assert_eq!(allocated.len(), 100);
+    assert!(
+        allocated
+            .iter()
+            .all(|&(capability, _)| capability == MemoryCapability::Internal)
+    );
+    // Each grow frees the previous buffer right away.
+    assert_eq!(heap.live(), 1);
+
+    drop(vec);
+    assert_eq!(heap.live(), 0);
+    assert_eq!(heap.freed().len(), allocated.len());
+}
+
+#[test]
+fn drop_frees_once_with_the_allocated_layout() {
+    let heap = CountingHeap::new();
+    let vec = RegionVec::<u64, _>::with_capacity_in(MemoryCapability::External, 10, &heap).unwrap();
+    assert_eq!(vec.capacity(), 10);
+    drop(vec);
+
+    let layout = Layout::array::<u64>(10).unwrap();
+    assert_eq!(heap.allocated(), [(MemoryCapability::External, layout)]);
+    assert_eq!(heap.freed(), [layout]);
+}
+
+#[test]
+fn truncate_and_clear_drop_elements() {
+    let heap = CountingHeap::new();
+    let value = Rc::new(());
+    let mut vec = RegionVec::with_capacity_in(MemoryCapability::External, 5, &heap).unwrap();
+    for _ in 0..5 {
+        vec.push_within_capacity(value.clone()).unwrap();
+    }
+    assert_eq!(Rc::strong_count(&value), 6);
+
+    vec.truncate(10);
+    assert_eq!(vec.len(), 5);
+    vec.truncate(2);
+    assert_eq!(vec.len(), 2);
+    assert_eq!(Rc::strong_count(&value), 3);
+
+    let popped = vec.pop().unwrap();
+    assert_eq!(Rc::strong_count(&value), 3);
+    drop(popped);
+
+    vec.clear();
+    assert!(vec.is_empty());
+    assert_eq!(Rc::strong_count(&value), 1);
+    // Clearing keeps the buffer.
+    assert_eq!(vec.capacity(), 5);
+    assert_eq!(heap.live(), 1);
+
+    vec.push_within_capacity(value.clone()).unwrap();
+    drop(vec);
+    assert_eq!(Rc::strong_count(&value), 1);
+    assert_eq!(heap.live(), 0);
+}
+
+#[test]
+fn zero_sized_types_never_allocate() {
+    let heap = CountingHeap::new();
+
+    let mut vec = RegionVec::new_in(MemoryCapability::Internal, &heap);
+    for _ in 0..1000 {
+        vec.push_within_capacity(()).unwrap();
+    }
+    assert_eq!(vec.len(), 1000);
+    assert_eq!(vec.capacity(), usize::MAX);
+    drop(vec);
+
+    let empty =
+        RegionVec::<u32, _>::with_capacity_in(MemoryCapability::Internal, 0, &heap).unwrap();
+    drop(empty);
+
+    assert!(heap.allocated().is_empty());
+    assert!(heap.freed().is_empty());
+}
+
+#[test]
+fn out_of_memory_is_reported() {
+    let heap = CountingHeap::with_limit(64);
+
+    let result = RegionVec::<u32, _>::with_capacity_in(MemoryCapability::External, 100, &heap);
+    assert!(matches!(
+        result,
+        Err(AllocError::OutOfMemory {
+            capability: MemoryCapability::External,
+            size: 400,
+        })
+    ));
+
+    // A failed grow leaves the vector as it was.
+    let mut vec = RegionVec::with_capacity_in(MemoryCapability::Internal, 16, &heap).unwrap();
+    for value in 0..16u32 {
+        vec.push_within_capacity(value).unwrap();
+    }
+    assert_eq!(vec.push_within_capacity(16), Err(16));
+    assert!(matches!(
+        vec.try_reserve_exact(1),
+        Err(AllocError::OutOfMemory {
+            capability: MemoryCapability::Internal,
+            ..
+        })
+    ));
+    assert!(vec.iter().copied().eq(0..16));
+    assert!(matches!(
+        vec.try_reserve_exact(usize::MAX),
+        Err(AllocError::OutOfMemory { .. })
+    ));
+
+    drop(vec);
+    assert_eq!(heap.live(), 0);
+}
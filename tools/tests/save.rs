@@ -0,0 +1,167 @@
+// The firmware's save slots, run on the host against image files.
+
+#[allow(dead_code)]
+#[path = "../../src/bin/save/mod.rs"]
+mod save;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use save::{FileStorage, SLOT_SIZE, SLOTS, SaveData, SaveError, SaveSlots, crc32};
+
+const CAPACITY: usize = (SLOTS * SLOT_SIZE) as usize;
+
+// A fresh image per test, tests run in parallel.
+fn image(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!(
+        "esp32-game-save-{}-{}.bin",
+        name,
+        std::process::id()
+    ));
+    fs::remove_file(&path).ok();
+    path
+}
+
+fn open(path: &Path) -> SaveSlots<FileStorage> {
+    SaveSlots::new(FileStorage::open(path, CAPACITY).unwrap()).unwrap()
+}
+
+fn data(score: u32, volume: u8) -> SaveData {
+    let mut data = SaveData {
+        key_map: 1,
+        brightness: 60,
+        volume,
+        ..SaveData::default()
+    };
+    data.high_scores.insert(score);
+    data
+}
+
+fn load(path: &Path) -> (Result<(), SaveError<std::io::Error>>, SaveData) {
+    let mut loaded = SaveData::default();
+    let result = open(path).load(&mut loaded);
+    (result, loaded)
+}
+
+fn sequence(image: &[u8], slot: u32) -> u32 {
+    let at = (slot * SLOT_SIZE) as usize + 8;
+    u32::from_le_bytes(image[at..at + 4].try_into().unwrap())
+}
+
+// Rewrites the sequence number of a slot, with a matching CRC.
+fn set_sequence(image: &mut [u8], slot: u32, sequence: u32) {
+    let at = (slot * SLOT_SIZE) as usize;
+    let len = u16::from_le_bytes([image[at + 6], image[at + 7]]) as usize;
+    image[at + 8..at + 12].copy_from_slice(&sequence.to_le_bytes());
+
+    let mut covered = image[at..at + 12].to_vec();
+    covered.extend_from_slice(&image[at + 16..at + 16 + len]);
+    image[at + 12..at + 16].copy_from_slice(&crc32(&covered).to_le_bytes());
+}
+
+#[test]
+fn erased_storage_loads_nothing() {
+    let path = image("erased");
+
+    let (result, loaded) = load(&path);
+    assert!(matches!(result, Err(SaveError::NoSave)));
+    assert_eq!(loaded, SaveData::default());
+    assert_eq!(fs::read(&path).unwrap(), vec![0xFF; CAPACITY]);
+
+    fs::write(&path, vec![0u8; CAPACITY]).unwrap();
+    let (result, loaded) = load(&path);
+    assert!(matches!(result, Err(SaveError::NoSave)));
+    assert_eq!(loaded, SaveData::default());
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn saves_alternate_between_slots() {
+    let path = image("alternate");
+    let mut slots = open(&path);
+
+    for (round, volume) in [10, 20, 30].into_iter().enumerate() {
+        assert!(slots.save(&data(100, volume)).unwrap());
+        let image = fs::read(&path).unwrap();
+        let slot = round as u32 % SLOTS;
+        assert_eq!(&image[(slot * SLOT_SIZE) as usize..][..4], b"SAVE");
+        assert_eq!(sequence(&image, slot), round as u32);
+    }
+    // Unchanged data is not written again.
+    assert!(!slots.save(&data(100, 30)).unwrap());
+    drop(slots);
+
+    let (result, loaded) = load(&path);
+    result.unwrap();
+    assert_eq!(loaded, data(100, 30));
+
+    // Picks up where it left off, the next save goes over the older slot.
+    let mut slots = open(&path);
+    slots.load(&mut SaveData::default()).unwrap();
+    slots.save(&data(100, 40)).unwrap();
+    assert_eq!(sequence(&fs::read(&path).unwrap(), 1), 3);
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn corrupted_newest_slot_falls_back() {
+    let path = image("corrupted");
+    let mut slots = open(&path);
+    slots.save(&data(100, 10)).unwrap();
+    slots.save(&data(200, 20)).unwrap();
+    drop(slots);
+
+    // Flip a bit in the body of the newest save, in slot 1.
+    let mut image = fs::read(&path).unwrap();
+    image[SLOT_SIZE as usize + 20] ^= 0x01;
+    fs::write(&path, &image).unwrap();
+
+    let (result, loaded) = load(&path);
+    result.unwrap();
+    assert_eq!(loaded, data(100, 10));
+
+    // The next save replaces the broken slot, not the good one.
+    let mut slots = open(&path);
+    slots.load(&mut SaveData::default()).unwrap();
+    slots.save(&data(300, 30)).unwrap();
+    drop(slots);
+    let (result, loaded) = load(&path);
+    result.unwrap();
+    assert_eq!(loaded, data(300, 30));
+    assert_eq!(sequence(&fs::read(&path).unwrap(), 0), 0);
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn sequence_numbers_wrap() {
+    let path = image("wrap");
+    let mut slots = open(&path);
+    slots.save(&data(100, 10)).unwrap();
+    slots.save(&data(200, 20)).unwrap();
+    drop(slots);
+
+    // Slot 0 just before the wrap, slot 1 just after it and so newer.
+    let mut image = fs::read(&path).unwrap();
+    set_sequence(&mut image, 0, u32::MAX);
+    set_sequence(&mut image, 1, 0);
+    fs::write(&path, &image).unwrap();
+
+    let (result, loaded) = load(&path);
+    result.unwrap();
+    assert_eq!(loaded, data(200, 20));
+
+    // And the other way around.
+    set_sequence(&mut image, 0, 0);
+    set_sequence(&mut image, 1, u32::MAX);
+    fs::write(&path, &image).unwrap();
+
+    let mut slots = open(&path);
+    let mut loaded = SaveData::default();
+    slots.load(&mut loaded).unwrap();
+    assert_eq!(loaded, data(100, 10));
+    slots.save(&data(300, 30)).unwrap();
+    assert_eq!(sequence(&fs::read(&path).unwrap(), 1), 1);
+    fs::remove_file(&path).ok();
+}